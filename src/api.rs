@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+
+use crate::proof::Proof;
+
+/// Body of every error response from the API routes. `field` names the multipart field a `missing_field` error is
+/// about.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ErrorResponse {
+    pub error: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
+
+/// Response of `GET /health`. `wasm_bucket` is false when the deployment lacks the R2 binding for modules.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HealthResponse {
+    pub status: String,
+    pub wasm_bucket: bool,
+}
+
+/// Response of `GET /version`, identifying the deployed build. `built_at` is in seconds since the Unix epoch.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VersionResponse {
+    pub version: String,
+    pub git_sha: String,
+    pub built_at: u64,
+}
+
+/// Where a proof is in its verification.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProofState {
+    Pending,
+    Verified,
+    Rejected,
+}
+
+impl ProofState {
+    /// Whether the state can no longer change.
+    pub fn is_terminal(self) -> bool {
+        !matches!(self, ProofState::Pending)
+    }
+}
+
+/// Response of `GET /proofs/:wasm_hash/:proof_hash/status`. `result` is the verified hash once the state is `verified`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProofStatusResponse {
+    pub state: ProofState,
+    pub result: Option<u64>,
+}
+
+/// Response of `POST /validate`. `size` is the module's length in bytes, `exported_functions` how many functions it
+/// exports, test or not.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ValidateResponse {
+    pub func: String,
+    pub arg: u64,
+    pub result: u64,
+    pub size: usize,
+    pub exported_functions: usize,
+}
+
+/// Response of `POST /upload_wasm`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UploadWasmResponse {
+    pub hash: String,
+    pub size: usize,
+    pub already_existed: bool,
+}
+
+/// Response of `GET /proofs/:wasm_hash`. `cursor` is set when there are more proofs to fetch by passing it back as
+/// `?cursor=`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProofListResponse {
+    pub proofs: Vec<Proof>,
+    pub cursor: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Check the JSON shape returned by `POST /upload_wasm`
+    #[test]
+    fn upload_wasm_response_json_unit_test_1() {
+        let response = UploadWasmResponse {
+            hash: "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".into(),
+            size: 1234,
+            already_existed: false,
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "hash": "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "size": 1234,
+                "already_existed": false
+            })
+        );
+        let round_trip: UploadWasmResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(round_trip, response);
+    }
+
+    // Check the JSON shape returned by `POST /validate`
+    #[test]
+    fn validate_response_json_unit_test_1() {
+        let response = ValidateResponse {
+            func: "test".into(),
+            arg: 42,
+            result: 1764,
+            size: 45,
+            exported_functions: 1,
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "func": "test", "arg": 42, "result": 1764, "size": 45, "exported_functions": 1 })
+        );
+        let round_trip: ValidateResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(round_trip, response);
+    }
+
+    // An empty page still lists `proofs`, with a null cursor
+    #[test]
+    fn proof_list_response_json_unit_test_1() {
+        let response = ProofListResponse {
+            proofs: Vec::new(),
+            cursor: None,
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json, serde_json::json!({ "proofs": [], "cursor": null }));
+        let round_trip: ProofListResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(round_trip, response);
+    }
+
+    // Errors are a single `error` string
+    #[test]
+    fn error_response_json_unit_test_1() {
+        let response = ErrorResponse {
+            error: "WASM not found".into(),
+            field: None,
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json, serde_json::json!({ "error": "WASM not found" }));
+        let round_trip: ErrorResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(round_trip, response);
+
+        let response = ErrorResponse {
+            error: "missing_field".into(),
+            field: Some("file".into()),
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json, serde_json::json!({ "error": "missing_field", "field": "file" }));
+    }
+
+    // Check the JSON shape returned by `GET /health`
+    #[test]
+    fn health_response_json_unit_test_1() {
+        let response = HealthResponse {
+            status: "ok".into(),
+            wasm_bucket: true,
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json, serde_json::json!({ "status": "ok", "wasm_bucket": true }));
+        let round_trip: HealthResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(round_trip, response);
+    }
+
+    // Check the JSON shape returned by `GET /version`
+    #[test]
+    fn version_response_json_unit_test_1() {
+        let response = VersionResponse {
+            version: "0.1.0".into(),
+            git_sha: "ee1418f".into(),
+            built_at: 1_700_000_000,
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "version": "0.1.0", "git_sha": "ee1418f", "built_at": 1_700_000_000 })
+        );
+        let round_trip: VersionResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(round_trip, response);
+    }
+
+    // Proof states are lowercase strings, and only `pending` is not final
+    #[test]
+    fn proof_status_response_json_unit_test_1() {
+        let response = ProofStatusResponse {
+            state: ProofState::Verified,
+            result: Some(1764),
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json, serde_json::json!({ "state": "verified", "result": 1764 }));
+        let round_trip: ProofStatusResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(round_trip, response);
+
+        assert_eq!(serde_json::to_value(ProofState::Pending).unwrap(), "pending");
+        assert_eq!(serde_json::to_value(ProofState::Rejected).unwrap(), "rejected");
+        assert!(!ProofState::Pending.is_terminal());
+        assert!(ProofState::Verified.is_terminal() && ProofState::Rejected.is_terminal());
+    }
+}
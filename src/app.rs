@@ -1,4 +1,6 @@
-use crate::components::{MessageContext, MessageSeverity, Messages};
+use crate::components::{
+    report_server_error, MessageContext, MessageSeverity, Messages, MyProofs, ProofDetail, UploadWasm,
+};
 use crate::github::*;
 use leptos::prelude::*;
 use leptos::task::*;
@@ -7,40 +9,97 @@ use leptos_router::components::*;
 use leptos_router::hooks::{use_navigate, use_query};
 use leptos_router::params::Params;
 use leptos_router::*;
+use serde::{Deserialize, Serialize};
 use server_fn::error::NoCustomError;
+use std::fmt::Write;
 use std::sync::Arc;
+use std::time::Duration;
 use web_sys::MouseEvent;
 
-const GITHUB_CLIENT_ID: &str = "Ov23lixO0S9pamhwo1u7";
+const OAUTH_STATE_KEY: &str = "github_oauth_state";
+
+/// How long a pending login's `state` token stays valid.
+const OAUTH_STATE_MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+const ORG_CACHE_KEY: &str = "github_org_cache";
+
+/// How long cached organizations are shown without asking GitHub again.
+const ORG_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// What a successful code exchange yields: the token and the scopes GitHub granted with it, comma separated.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GrantedToken {
+    pub access_token: String,
+    pub scope: String,
+}
 
 #[server(ExchangeToken, "/api")]
 #[worker::send]
-pub async fn exchange_token(code: String) -> Result<String, ServerFnError> {
+pub async fn exchange_token(code: String) -> Result<GrantedToken, ServerFnError> {
     use axum::Extension;
+    use http::{header, HeaderMap};
     use leptos_axum::extract;
     use worker::Env;
 
     let Extension(env): Extension<Arc<Env>> = extract().await?;
+    let headers: HeaderMap = extract().await?;
     let client_secret = env.secret("GITHUB_CLIENT_SECRET")?.to_string();
 
-    let client = reqwest::Client::new();
-    let response = client
-        .post("https://github.com/login/oauth/access_token")
-        .header("Accept", "application/json")
-        .form(&[
-            ("client_id", GITHUB_CLIENT_ID),
-            ("client_secret", &client_secret),
-            ("code", &code),
-        ])
-        .send()
-        .await?;
-
-    if response.status().is_success() {
-        let token_response = response.json::<TokenResponse>().await?;
-        Ok(token_response.access_token)
+    // The same client id the login button authorized with, GitHub only exchanges codes for the app that issued them.
+    let mut form = vec![
+        ("client_id", GITHUB_CLIENT_ID.to_string()),
+        ("client_secret", client_secret),
+        ("code", code),
+    ];
+    // GitHub insists the redirect_uri matches the one sent to the authorize step. That step took the origin of the
+    // page, which the browser sends along as `Origin`, unless `BASE_URL` pins it.
+    let base_url = env.var("BASE_URL").map(|url| url.to_string()).ok().or_else(|| {
+        headers
+            .get(header::ORIGIN)
+            .and_then(|origin| origin.to_str().ok())
+            .map(str::to_string)
+    });
+    if let Some(base_url) = base_url {
+        form.push(("redirect_uri", oauth_redirect_uri(&base_url)));
+    }
+
+    // The OAuth endpoints answer in JSON only when asked for `application/json`. A 5xx is retried briefly, a 4xx is
+    // about the request and would only fail again.
+    let client = build_github_client();
+    let url = format!("{}/login/oauth/access_token", github_web_url().trim_end_matches('/'));
+    let response = send_with_retry(
+        || client.post(&url).header("Accept", "application/json").form(&form),
+        &url,
+        TOKEN_EXCHANGE_RETRIES,
+        GITHUB_RETRY_DELAY,
+    )
+    .await?;
+
+    // GitHub reports a bad code with a 200 and an error body, so both are parsed from the body.
+    let status = response.status();
+    let body = response.text().await?;
+    if let Ok(error) = serde_json::from_str::<ErrorResponse>(&body) {
+        return Err(ServerFnError::ServerError::<NoCustomError>(error.message()));
+    }
+    if status.is_success() {
+        let token_response = serde_json::from_str::<TokenResponse>(&body)?;
+        let missing = missing_scopes(&token_response.scope);
+        if !missing.is_empty() {
+            return Err(ServerFnError::ServerError::<NoCustomError>(format!(
+                "GitHub did not grant the {} permission{} the app needs",
+                missing.join(", "),
+                if missing.len() == 1 { "" } else { "s" }
+            )));
+        }
+        Ok(GrantedToken {
+            access_token: token_response.access_token,
+            scope: token_response.scope,
+        })
     } else {
-        let error = response.json::<ErrorResponse>().await?;
-        Err(ServerFnError::ServerError::<NoCustomError>(error.error))
+        Err(ServerFnError::ServerError::<NoCustomError>(format!(
+            "GitHub answered {}",
+            status
+        )))
     }
 }
 
@@ -48,6 +107,8 @@ pub async fn exchange_token(code: String) -> Result<String, ServerFnError> {
 pub struct UserContext {
     logged_in: RwSignal<bool>,
     token: RwSignal<Option<String>>,
+    /// Scopes granted with the token, `None` when they weren't recorded.
+    scope: RwSignal<Option<String>>,
     user: LocalResource<Option<User>>,
 }
 
@@ -58,37 +119,62 @@ impl UserContext {
 
         let user = LocalResource::new(move || async move {
             match token.get() {
-                Some(token) => UserAccessToken::from_string(token).user().await.ok(),
+                Some(token) => GithubClient::new(github_api_url(), UserAccessToken::from_string(token))
+                    .current_user()
+                    .await
+                    .ok(),
                 None => None,
             }
         });
 
+        let scope = RwSignal::new(None);
         Effect::new(move |_| {
             if let Some(access_token) = get_token_from_storage() {
+                scope.set(get_scope_from_storage());
                 token.set(Some(access_token));
                 logged_in.set(true);
             }
         });
 
-        Self { logged_in, token, user }
+        Self {
+            logged_in,
+            token,
+            scope,
+            user,
+        }
     }
 
-    pub fn login(&self, token: String) {
-        set_token_storage(&token);
-        self.token.set(Some(token));
+    pub fn login(&self, granted: GrantedToken) {
+        set_token_storage(&granted.access_token, &granted.scope);
+        self.scope.set(Some(granted.scope));
+        self.token.set(Some(granted.access_token));
         self.logged_in.set(true);
     }
 
     pub fn logout(&self) {
         remove_token_storage();
+        remove_org_cache();
         self.token.set(None);
+        self.scope.set(None);
         self.logged_in.set(false);
     }
 
+    /// Whether the login was granted `scope`. Tokens stored before scopes were recorded count as having everything
+    /// the app asked for.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope.get().map_or(true, |granted| has_scope(&granted, scope))
+    }
+
     pub fn get_token(&self) -> Option<String> {
         self.token.get()
     }
 
+    /// A GitHub client for the logged in user. Tracks the token, so callers rerun when the user logs in or out.
+    pub fn github(&self) -> Option<GithubClient> {
+        self.get_token()
+            .map(|token| GithubClient::new(github_api_url(), UserAccessToken::from_string(token)))
+    }
+
     pub fn is_logged_in(&self) -> bool {
         self.logged_in.get()
     }
@@ -98,123 +184,738 @@ impl UserContext {
     }
 }
 
-fn set_token_storage(token: &str) {
+/// Returns the reaction to a GitHub call failing with `401`: the stored token is dropped, the user is asked to log in
+/// again and sent back home. Only the first failure does anything, so lists loading in parallel report it once.
+fn use_expired_token_handler() -> impl Fn() + Clone + 'static {
+    let user_ctx = expect_context::<UserContext>();
+    let message_ctx = expect_context::<MessageContext>();
+    let navigate = use_navigate();
+    move || {
+        if !user_ctx.logged_in.get_untracked() {
+            return;
+        }
+        user_ctx.logout();
+        message_ctx.add(
+            "Your GitHub session has expired, please log in again",
+            MessageSeverity::Error,
+        );
+        navigate("/", NavigateOptions::default());
+    }
+}
+
+fn set_token_storage(token: &str, scope: &str) {
     if let Some(storage) = window().local_storage().ok().flatten() {
         let _ = storage.set_item("github_token", token);
+        let _ = storage.set_item("github_token_scope", scope);
     }
 }
 
 fn remove_token_storage() {
     if let Some(storage) = window().local_storage().ok().flatten() {
         let _ = storage.remove_item("github_token");
+        let _ = storage.remove_item("github_token_scope");
     }
 }
 
-fn get_token_from_storage() -> Option<String> {
+fn get_scope_from_storage() -> Option<String> {
     window()
         .local_storage()
         .ok()
         .flatten()
-        .and_then(|storage| storage.get_item("github_token").ok().flatten())
+        .and_then(|storage| storage.get_item("github_token_scope").ok().flatten())
+}
+
+// A stored token without surrounding whitespace, or `None` if nothing is left of it. An empty token would only be
+// sent as a bare `Bearer ` and be refused with a 401.
+fn usable_token(token: Option<String>) -> Option<String> {
+    token
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+fn get_token_from_storage() -> Option<String> {
+    usable_token(
+        window()
+            .local_storage()
+            .ok()
+            .flatten()
+            .and_then(|storage| storage.get_item("github_token").ok().flatten()),
+    )
+}
+
+/// Where the chosen theme is kept between visits.
+const THEME_KEY: &str = "theme";
+
+/// Colour scheme of the whole UI.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Theme {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl Theme {
+    fn as_str(self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        }
+    }
+
+    fn toggled(self) -> Self {
+        match self {
+            Theme::Light => Theme::Dark,
+            Theme::Dark => Theme::Light,
+        }
+    }
+
+    /// The stored preference if there is a valid one, the system's otherwise.
+    fn initial(stored: Option<&str>, prefers_dark: bool) -> Self {
+        match stored {
+            Some("light") => Theme::Light,
+            Some("dark") => Theme::Dark,
+            _ if prefers_dark => Theme::Dark,
+            _ => Theme::Light,
+        }
+    }
+
+    /// Classes for `<body>`. Tailwind's `dark:` variants only apply below the `dark` class, so the body's own
+    /// background is picked here.
+    fn body_class(self) -> &'static str {
+        match self {
+            Theme::Light => "bg-sky-100",
+            Theme::Dark => "dark bg-gray-900",
+        }
+    }
+}
+
+fn load_theme() -> Theme {
+    let stored = window()
+        .local_storage()
+        .ok()
+        .flatten()
+        .and_then(|storage| storage.get_item(THEME_KEY).ok().flatten());
+    let prefers_dark = window()
+        .match_media("(prefers-color-scheme: dark)")
+        .ok()
+        .flatten()
+        .is_some_and(|query| query.matches());
+    Theme::initial(stored.as_deref(), prefers_dark)
+}
+
+fn store_theme(theme: Theme) {
+    if let Some(storage) = window().local_storage().ok().flatten() {
+        let _ = storage.set_item(THEME_KEY, theme.as_str());
+    }
+}
+
+#[component]
+fn ThemeToggle() -> impl IntoView {
+    let theme = expect_context::<RwSignal<Theme>>();
+
+    view! {
+        <button
+            class="px-3 py-1 text-sm rounded-full bg-sky-600 hover:bg-sky-500"
+            aria-label="Toggle dark mode"
+            on:click=move |_| {
+                let next = theme.get().toggled();
+                store_theme(next);
+                theme.set(next);
+            }
+        >
+            {move || match theme.get() {
+                Theme::Light => "Dark",
+                Theme::Dark => "Light",
+            }}
+        </button>
+    }
+}
+
+/// The organizations of `user` and their repositories, as last fetched from GitHub.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct OrgCache {
+    user: String,
+    fetched_at_ms: u64,
+    orgs: Vec<(Organization, Vec<Repository>)>,
+}
+
+impl OrgCache {
+    fn is_fresh(&self, now: Duration) -> bool {
+        now.saturating_sub(Duration::from_millis(self.fetched_at_ms)) < ORG_CACHE_TTL
+    }
+}
+
+fn store_org_cache(user: &str, orgs: &[(Organization, Vec<Repository>)]) {
+    let cache = OrgCache {
+        user: user.to_string(),
+        fetched_at_ms: unix_time().as_millis() as u64,
+        orgs: orgs.to_vec(),
+    };
+    if let (Some(storage), Ok(json)) = (window().local_storage().ok().flatten(), serde_json::to_string(&cache)) {
+        let _ = storage.set_item(ORG_CACHE_KEY, &json);
+    }
+}
+
+// A cache written for someone else, or by an older version of the app, counts as missing.
+fn load_org_cache(user: &str) -> Option<OrgCache> {
+    let json = window()
+        .local_storage()
+        .ok()
+        .flatten()?
+        .get_item(ORG_CACHE_KEY)
+        .ok()
+        .flatten()?;
+    serde_json::from_str::<OrgCache>(&json)
+        .ok()
+        .filter(|cache| cache.user == user)
+}
+
+fn remove_org_cache() {
+    if let Some(storage) = window().local_storage().ok().flatten() {
+        let _ = storage.remove_item(ORG_CACHE_KEY);
+    }
+}
+
+fn unix_time() -> Duration {
+    instant::SystemTime::now()
+        .duration_since(instant::SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Where GitHub sends the user back after authorizing, for the site served from `base_url`.
+fn oauth_redirect_uri(base_url: &str) -> String {
+    format!("{}/oauth/callback", base_url.trim_end_matches('/'))
+}
+
+fn generate_oauth_state() -> Option<String> {
+    let mut bytes = [0u8; 16];
+    window()
+        .crypto()
+        .ok()?
+        .get_random_values_with_u8_array(&mut bytes)
+        .ok()?;
+    Some(bytes.iter().fold(String::with_capacity(32), |mut state, b| {
+        let _ = write!(state, "{:02x}", b);
+        state
+    }))
+}
+
+fn store_oauth_state(state: &str, issued_at: Duration) {
+    if let Some(storage) = window().local_storage().ok().flatten() {
+        let _ = storage.set_item(OAUTH_STATE_KEY, &format!("{}:{}", state, issued_at.as_millis()));
+    }
+}
+
+// The state is single use, so reading it also removes it.
+fn take_oauth_state() -> Option<String> {
+    let storage = window().local_storage().ok().flatten()?;
+    let state = storage.get_item(OAUTH_STATE_KEY).ok().flatten();
+    let _ = storage.remove_item(OAUTH_STATE_KEY);
+    state
+}
+
+/// Checks the `state` GitHub handed back against the `"{state}:{issued_at_ms}"` entry stored when the login started.
+fn verify_oauth_state(stored: Option<&str>, received: Option<&str>, now: Duration) -> Result<(), &'static str> {
+    let (expected, issued_at) = stored
+        .and_then(|stored| stored.split_once(':'))
+        .ok_or("no login in progress")?;
+    let issued_at = issued_at
+        .parse::<u64>()
+        .map(Duration::from_millis)
+        .map_err(|_| "malformed login state")?;
+    if expected.is_empty() || received != Some(expected) {
+        return Err("login state mismatch");
+    }
+    if now.saturating_sub(issued_at) > OAUTH_STATE_MAX_AGE {
+        return Err("login attempt expired");
+    }
+    Ok(())
 }
 
 #[component]
 fn LoginButton() -> impl IntoView {
-    let auth_url = format!(
-        "https://github.com/login/oauth/authorize?client_id={}&redirect_uri=http://127.0.0.1:8787/oauth/callback&scope=read:project+read:org",
-        GITHUB_CLIENT_ID
-    );
+    let message_ctx = expect_context::<MessageContext>();
+
+    // The state has to be generated in the browser, so the authorize URL is only built once the button is clicked.
+    let login = move |_| {
+        let Some(state) = generate_oauth_state() else {
+            message_ctx.add("Failed to login: no secure random source", MessageSeverity::Error);
+            return;
+        };
+        store_oauth_state(&state, unix_time());
+        let redirect_uri = oauth_redirect_uri(&window().location().origin().unwrap_or_default());
+        let auth_url = url::Url::parse_with_params(
+            &format!("{}/login/oauth/authorize", github_web_url()),
+            &[
+                ("client_id", GITHUB_CLIENT_ID),
+                ("redirect_uri", &redirect_uri),
+                ("scope", &GITHUB_SCOPES.join(" ")),
+                ("state", &state),
+            ],
+        )
+        .expect("authorize URL is valid");
+        let _ = window().location().set_href(auth_url.as_str());
+    };
 
     view! {
-        <a
-            href=auth_url
+        <button
             class="inline-block px-4 py-2 bg-gray-900 text-white rounded hover:bg-gray-700 transition-colors"
+            on:click=login
         >
             "Login with GitHub"
-        </a>
+        </button>
+    }
+}
+
+/// Which repositories the listings show, by their `private` flag.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Visibility {
+    #[default]
+    All,
+    Public,
+    Private,
+}
+
+impl Visibility {
+    const ALL: [Visibility; 3] = [Visibility::All, Visibility::Public, Visibility::Private];
+
+    fn label(self) -> &'static str {
+        match self {
+            Visibility::All => "All",
+            Visibility::Public => "Public only",
+            Visibility::Private => "Private only",
+        }
+    }
+
+    fn allows(self, repo: &Repository) -> bool {
+        match self {
+            Visibility::All => true,
+            Visibility::Public => !repo.private,
+            Visibility::Private => repo.private,
+        }
     }
 }
 
 #[component]
-fn RepositoryList() -> impl IntoView {
-    let repos = LocalResource::new(move || async move {
-        match get_access_token_from_storage() {
-            Some(token) => token.user_repositories().await.ok().unwrap_or_default(),
-            None => vec![],
+fn VisibilityFilter(visibility: RwSignal<Visibility>) -> impl IntoView {
+    view! {
+        <div class="inline-flex rounded border overflow-hidden dark:border-gray-600">
+            {Visibility::ALL.map(|option| {
+                view! {
+                    <button
+                        class=move || {
+                            if visibility.get() == option {
+                                "px-3 py-1 text-sm bg-gray-800 text-white"
+                            } else {
+                                "px-3 py-1 text-sm bg-white text-gray-700 hover:bg-gray-100 dark:bg-gray-800 dark:text-gray-300 dark:hover:bg-gray-700"
+                            }
+                        }
+                        on:click=move |_| visibility.set(option)
+                    >
+                        {option.label()}
+                    </button>
+                }
+            }).collect_view()}
+        </div>
+    }
+}
+
+/// How the repository listing is ordered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum RepositoryOrder {
+    #[default]
+    RecentlyUpdated,
+    Name,
+}
+
+impl RepositoryOrder {
+    const ALL: [RepositoryOrder; 2] = [RepositoryOrder::RecentlyUpdated, RepositoryOrder::Name];
+
+    fn label(self) -> &'static str {
+        match self {
+            RepositoryOrder::RecentlyUpdated => "Recently updated",
+            RepositoryOrder::Name => "Name",
+        }
+    }
+
+    // Newest first, by whichever of the last push and the last update is later. Ties and name order go by full name,
+    // ignoring case.
+    fn compare(self, a: &Repository, b: &Repository) -> std::cmp::Ordering {
+        let by_name = || a.full_name.to_lowercase().cmp(&b.full_name.to_lowercase());
+        match self {
+            RepositoryOrder::RecentlyUpdated => {
+                let last_activity = |repo: &Repository| repo.pushed_at.max(repo.updated_at);
+                last_activity(b).cmp(&last_activity(a)).then_with(by_name)
+            }
+            RepositoryOrder::Name => by_name(),
+        }
+    }
+}
+
+/// Repositories rendered per page of the listing.
+const REPOSITORY_PAGE_SIZE: usize = 25;
+
+/// The slice of `total` repositories shown on `page`. Pages past the end show the last one.
+fn page_range(total: usize, page: usize) -> std::ops::Range<usize> {
+    let last_page = total.saturating_sub(1) / REPOSITORY_PAGE_SIZE;
+    let start = page.min(last_page) * REPOSITORY_PAGE_SIZE;
+    start..total.min(start + REPOSITORY_PAGE_SIZE)
+}
+
+/// Whether `repo` is shown for the search box contents `query`, matching `full_name` case-insensitively.
+fn repository_matches(repo: &Repository, query: &str) -> bool {
+    repo.full_name.to_lowercase().contains(&query.trim().to_lowercase())
+}
+
+/// Lets the user rerun the GitHub fetches. Resources read `generation` to refetch when it is bumped, and count
+/// themselves in `in_flight` while they run.
+#[derive(Clone, Copy)]
+struct Refresh {
+    generation: RwSignal<u32>,
+    in_flight: RwSignal<u32>,
+}
+
+impl Refresh {
+    fn new() -> Self {
+        Self {
+            generation: RwSignal::new(0),
+            in_flight: RwSignal::new(0),
         }
+    }
+
+    fn generation(&self) -> u32 {
+        self.generation.get()
+    }
+
+    fn bump(&self) {
+        self.generation.update(|generation| *generation += 1);
+    }
+
+    fn is_busy(&self) -> bool {
+        self.in_flight.get() > 0
+    }
+
+    async fn run<T>(self, fetch: impl std::future::Future<Output = T>) -> T {
+        self.in_flight.update(|n| *n += 1);
+        // A superseded resource drops its future mid-fetch, the guard still takes it off the count.
+        let _guard = InFlight(self.in_flight);
+        fetch.await
+    }
+}
+
+// Decrements the in-flight count of a [`Refresh`] when dropped.
+struct InFlight(RwSignal<u32>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        // The signal is gone too when the whole view was torn down.
+        let _ = self.0.try_update(|n| *n = n.saturating_sub(1));
+    }
+}
+
+#[component]
+fn RefreshButton(refresh: Refresh) -> impl IntoView {
+    view! {
+        <button
+            class="px-3 py-1 text-sm border rounded hover:bg-gray-100 disabled:opacity-50 dark:border-gray-600 dark:hover:bg-gray-700"
+            disabled=move || refresh.is_busy()
+            on:click=move |_| refresh.bump()
+        >
+            {move || if refresh.is_busy() { "Refreshing..." } else { "Refresh" }}
+        </button>
+    }
+}
+
+/// Placeholder cards shown while a repository listing loads.
+#[component]
+fn RepositorySkeleton(#[prop(default = 3)] rows: usize) -> impl IntoView {
+    view! {
+        <div class="space-y-2 animate-pulse" aria-busy="true">
+            {(0..rows).map(|_| view! {
+                <div class="p-4 border rounded dark:border-gray-700">
+                    <div class="h-4 w-1/3 bg-gray-200 rounded dark:bg-gray-700"></div>
+                    <div class="mt-2 h-3 w-1/2 bg-gray-100 rounded dark:bg-gray-700"></div>
+                </div>
+            }).collect_view()}
+        </div>
+    }
+}
+
+#[component]
+fn RepositoryCard(repo: Repository) -> impl IntoView {
+    view! {
+        <div class="p-4 border rounded hover:bg-gray-50 dark:border-gray-700 dark:hover:bg-gray-700">
+            <a
+                href=repo.html_url
+                target="_blank"
+                class="font-medium rounded hover:underline focus:outline-none focus-visible:ring-2 focus-visible:ring-sky-500 focus-visible:ring-offset-2 dark:focus-visible:ring-offset-gray-800"
+            >
+                {repo.name}
+            </a>
+            <span class="ml-2 text-sm text-gray-500 dark:text-gray-400">
+                {repo.full_name} " · " {if repo.private { "Private" } else { "Public" }}
+            </span>
+            {repo.description.map(|description| view! {
+                <p class="mt-1 text-sm text-gray-500 dark:text-gray-400">{description}</p>
+            })}
+        </div>
+    }
+}
+
+#[component]
+fn RepositoryList(visibility: RwSignal<Visibility>, refresh: Refresh) -> impl IntoView {
+    let message_ctx = expect_context::<MessageContext>();
+    let user_ctx = expect_context::<UserContext>();
+    let token_expired = use_expired_token_handler();
+
+    // Reading the token outside of the future makes the resource refetch whenever the user logs in or out.
+    let repos = LocalResource::new(move || {
+        refresh.generation();
+        let message_ctx = message_ctx.clone();
+        let token_expired = token_expired.clone();
+        let github = user_ctx.github();
+        refresh.run(async move {
+            match github {
+                Some(github) => {
+                    let (repos, error) = github.list_user_repos().await;
+                    match error {
+                        Some(GithubError::Unauthorized) => token_expired(),
+                        Some(GithubError::RateLimited { reset }) => message_ctx.add(
+                            RateLimited { reset }.describe(unix_time().as_secs()),
+                            MessageSeverity::Warn,
+                        ),
+                        Some(e) if repos.is_empty() => {
+                            message_ctx.add(format!("Failed to load repositories: {}", e), MessageSeverity::Error)
+                        }
+                        Some(e) => message_ctx.add(
+                            format!("Only {} repositories could be loaded: {}", repos.len(), e),
+                            MessageSeverity::Warn,
+                        ),
+                        None => {}
+                    }
+                    repos
+                }
+                None => vec![],
+            }
+        })
+    });
+
+    let query = RwSignal::new(String::new());
+    let order = RwSignal::new(RepositoryOrder::default());
+    let page = RwSignal::new(0usize);
+
+    // Back to the first page whenever the selection changes.
+    Effect::new(move |_| {
+        query.track();
+        visibility.track();
+        order.track();
+        page.set(0);
     });
 
     view! {
-        <div class="space-y-4">
+        // Target of the skip link, focusable so keyboard users land here rather than just scrolling.
+        <section id="repositories" tabindex="-1" class="space-y-4 focus:outline-none">
             <h2 class="text-2xl font-bold">"Your Repositories"</h2>
+            <div class="flex space-x-2">
+                <input
+                    type="search"
+                    placeholder="Filter repositories"
+                    class="flex-1 px-3 py-2 border rounded dark:bg-gray-700 dark:border-gray-600"
+                    prop:value=query
+                    on:input=move |ev| query.set(event_target_value(&ev))
+                />
+                <select
+                    class="px-3 py-2 border rounded bg-white dark:bg-gray-700 dark:border-gray-600"
+                    aria-label="Sort repositories"
+                    on:change=move |ev| {
+                        let label = event_target_value(&ev);
+                        if let Some(option) = RepositoryOrder::ALL.into_iter().find(|option| option.label() == label) {
+                            order.set(option);
+                        }
+                    }
+                >
+                    {RepositoryOrder::ALL.map(|option| view! {
+                        <option value=option.label() selected=move || order.get() == option>
+                            {option.label()}
+                        </option>
+                    }).collect_view()}
+                </select>
+            </div>
             <div class="space-y-2">
-                <Suspense fallback=move || view! { <p>"Loading..."</p> }.into_any()>
+                <Suspense fallback=move || view! { <RepositorySkeleton/> }.into_any()>
                     {move || Suspend::new(async move {
-                        repos.await.into_iter().map(|repo| {
-                            view! {
-                            <div class="p-4 border rounded hover:bg-gray-50">
-                                <a href=repo.html_url.clone() target="_blank" class="font-medium hover:underline">
-                                    {repo.full_name.clone()}
-                                </a>
-                                    <span class="ml-2 text-sm text-gray-500">
-                                        {if repo.private { "Private" } else { "Public" }}
-                                    </span>
+                        let query = query.get();
+                        let visibility = visibility.get();
+                        let order = order.get();
+                        let mut repos = repos
+                            .await
+                            .into_iter()
+                            .filter(|repo| visibility.allows(repo) && repository_matches(repo, &query))
+                            .collect::<Vec<_>>();
+                        repos.sort_by(|a, b| order.compare(a, b));
+                        if repos.is_empty() && (!query.trim().is_empty() || visibility != Visibility::All) {
+                            return view! { <p class="text-gray-500 dark:text-gray-400">"No repositories match"</p> }.into_any();
+                        }
+                        let total = repos.len();
+                        let range = page_range(total, page.get());
+                        let current = range.start / REPOSITORY_PAGE_SIZE;
+                        let (start, end) = (range.start, range.end);
+                        view! {
+                            {repos.drain(range).map(|repo| {
+                                view! { <RepositoryCard repo/> }
+                            }).collect_view()}
+                            <Show when=move || { total > REPOSITORY_PAGE_SIZE }>
+                                <div class="flex items-center justify-between text-sm text-gray-600 dark:text-gray-400">
+                                    <span>{format!("Showing {}–{} of {}", start + 1, end, total)}</span>
+                                    <div class="space-x-2">
+                                        <button
+                                            class="px-3 py-1 border rounded hover:bg-gray-100 disabled:opacity-50"
+                                            disabled=start == 0
+                                            on:click=move |_| page.set(current.saturating_sub(1))
+                                        >
+                                            "Prev"
+                                        </button>
+                                        <button
+                                            class="px-3 py-1 border rounded hover:bg-gray-100 disabled:opacity-50"
+                                            disabled=end >= total
+                                            on:click=move |_| page.set(current + 1)
+                                        >
+                                            "Next"
+                                        </button>
+                                    </div>
                                 </div>
-                            }
-                        }).collect_view()
+                            </Show>
+                        }.into_any()
                     })}
                 </Suspense>
             </div>
-        </div>
+        </section>
+    }
+}
+
+/// Fetches the organizations of `user` and their repositories. The flag is false when some of it failed to load, the
+/// failures are reported as messages.
+async fn fetch_org_repos(
+    github: &GithubClient,
+    user: &User,
+    message_ctx: &MessageContext,
+    token_expired: impl Fn(),
+) -> (Vec<(Organization, Vec<Repository>)>, bool) {
+    let warn_rate_limited =
+        |limit: RateLimited| message_ctx.add(limit.describe(unix_time().as_secs()), MessageSeverity::Warn);
+    let orgs = match github.list_user_orgs(&user.login).await {
+        Ok(orgs) => orgs,
+        Err(GithubError::Unauthorized) => {
+            token_expired();
+            return (vec![], false);
+        }
+        Err(GithubError::RateLimited { reset }) => {
+            warn_rate_limited(RateLimited { reset });
+            return (vec![], false);
+        }
+        Err(_) => return (vec![], false),
+    };
+    let results = map_bounded(&orgs, GITHUB_MAX_CONCURRENT_REQUESTS, |org| {
+        github.list_org_repos(&org.login)
+    })
+    .await;
+    // Orgs whose repositories failed to load are left out, the rest keep GitHub's order.
+    let mut rate_limited = None;
+    let mut unauthorized = false;
+    let mut complete = true;
+    let org_repos = orgs
+        .into_iter()
+        .zip(results)
+        .filter_map(|(org, result)| match result {
+            Ok(repositories) => Some((org, repositories)),
+            Err(e) => {
+                complete = false;
+                match e {
+                    GithubError::RateLimited { reset } => rate_limited = Some(RateLimited { reset }),
+                    GithubError::Unauthorized => unauthorized = true,
+                    _ => {}
+                }
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    if unauthorized {
+        token_expired();
+    } else if let Some(limit) = rate_limited {
+        warn_rate_limited(limit);
     }
+    (org_repos, complete)
 }
 
 #[component]
-fn OrganizationList() -> impl IntoView {
+fn OrganizationList(visibility: RwSignal<Visibility>, refresh: Refresh) -> impl IntoView {
+    let message_ctx = expect_context::<MessageContext>();
     let user_ctx = expect_context::<UserContext>();
+    let token_expired = use_expired_token_handler();
 
-    let org_data = LocalResource::new(move || async move {
-        match (get_access_token_from_storage(), user_ctx.user().await) {
-            (Some(token), Some(user)) => {
-                let orgs = token.organizations(&user.login).await.ok().unwrap_or_default();
-                let mut org_map = std::collections::HashMap::new();
-                for org in orgs {
-                    if let Ok(repositories) = token.org_repositories(&org.login).await {
-                        org_map.insert(org, repositories);
+    // Bumped when a background revalidation has updated the cache. A new refresh generation skips the cache.
+    let revision = RwSignal::new(0u32);
+    let seen_generation = StoredValue::new(refresh.generation.get_untracked());
+
+    // A fresh cache is shown as is. A stale one is shown while GitHub is asked again in the background, only complete
+    // results are cached.
+    let org_data = LocalResource::new(move || {
+        revision.track();
+        let generation = refresh.generation();
+        let message_ctx = message_ctx.clone();
+        let token_expired = token_expired.clone();
+        let github = user_ctx.github();
+        refresh.run(async move {
+            let (Some(github), Some(user)) = (github, user_ctx.user().await) else {
+                return vec![];
+            };
+            let cached = if generation != seen_generation.get_value() {
+                seen_generation.set_value(generation);
+                None
+            } else {
+                load_org_cache(&user.login)
+            };
+            match cached {
+                Some(cache) if cache.is_fresh(unix_time()) => cache.orgs,
+                Some(cache) => {
+                    spawn_local(async move {
+                        let (orgs, complete) = fetch_org_repos(&github, &user, &message_ctx, token_expired).await;
+                        if complete {
+                            store_org_cache(&user.login, &orgs);
+                            revision.update(|revision| *revision += 1);
+                        }
+                    });
+                    cache.orgs
+                }
+                None => {
+                    let (orgs, complete) = fetch_org_repos(&github, &user, &message_ctx, token_expired).await;
+                    if complete {
+                        store_org_cache(&user.login, &orgs);
                     }
+                    orgs
                 }
-                org_map
             }
-            _ => Default::default(),
-        }
+        })
     });
 
     view! {
         <div class="space-y-4">
             <h2 class="text-2xl font-bold">"Your Organizations"</h2>
             <div class="space-y-6">
-                <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                <Suspense fallback=move || view! { <RepositorySkeleton/> }>
                     <div>
                     { move || Suspend::new(async move {
+                        let visibility = visibility.get();
                         org_data.await.into_iter().map(|(org, repositories)| {
                             view! {
                                 <div class="space-y-2">
                                     <div class="flex items-center space-x-2">
-                                        <img src=org.avatar_url.clone() class="w-8 h-8 rounded-full" />
+                                        <Avatar url=org.avatar_url.clone() alt=org.login.clone() />
                                         <h3 class="text-xl font-semibold">{org.login.clone()}</h3>
                                     </div>
                                     <div class="ml-10 space-y-2">
-                                        {repositories.into_iter().map(|repo| {
-                                            view! {
-                                                <div class="p-4 border rounded hover:bg-gray-50">
-                                                    <a href=repo.html_url.clone() target="_blank" class="font-medium hover:underline">
-                                                        {repo.full_name.clone()}
-                                                    </a>
-                                                    <span class="ml-2 text-sm text-gray-500">
-                                                        {if repo.private { "Private" } else { "Public" }}
-                                                    </span>
-                                                </div>
-                                            }
+                                        {repositories.into_iter().filter(|repo| visibility.allows(repo)).map(|repo| {
+                                            view! { <RepositoryCard repo/> }
                                         }).collect_view()}
                                     </div>
                                 </div>
@@ -228,6 +929,27 @@ fn OrganizationList() -> impl IntoView {
     }
 }
 
+/// Shown in place of an avatar that failed to load, bundled so it can't fail the same way.
+const AVATAR_PLACEHOLDER: &str = "/avatar-placeholder.svg";
+
+/// A 32px round avatar that falls back to [`AVATAR_PLACEHOLDER`] when the image can't be loaded.
+#[component]
+fn Avatar(#[prop(into)] url: String, #[prop(into)] alt: String) -> impl IntoView {
+    let failed = RwSignal::new(false);
+
+    view! {
+        <img
+            src=move || if failed.get() { AVATAR_PLACEHOLDER.to_string() } else { url.clone() }
+            alt=alt
+            width="32"
+            height="32"
+            loading="lazy"
+            class="w-8 h-8 rounded-full"
+            on:error=move |_| failed.set(true)
+        />
+    }
+}
+
 #[component]
 fn UserDropdown(#[prop(into)] user_name: String, #[prop(into)] avatar_url: String) -> impl IntoView {
     let (is_open, set_is_open) = signal(false);
@@ -251,19 +973,26 @@ fn UserDropdown(#[prop(into)] user_name: String, #[prop(into)] avatar_url: Strin
                 on:click=toggle_dropdown
             />
             <Show when=move || is_open.get()>
-                <div class="absolute right-0 mt-2 w-48 bg-white rounded-md shadow-lg py-1 z-10">
-                    <div class="px-4 py-2 text-sm text-gray-700 border-b">
+                <div class="absolute right-0 mt-2 w-48 bg-white rounded-md shadow-lg py-1 z-10 dark:bg-gray-800">
+                    <div class="px-4 py-2 text-sm text-gray-700 border-b dark:text-gray-200 dark:border-gray-700">
                         {user_name.clone()}
                     </div>
+                    <a
+                        href="/my-proofs"
+                        class="block px-4 py-2 text-sm text-gray-700 hover:bg-gray-100 dark:text-gray-200 dark:hover:bg-gray-700"
+                        on:click=move |_| set_is_open.set(false)
+                    >
+                        "My proofs"
+                    </a>
                     <a
                         href="/settings"
-                        class="block px-4 py-2 text-sm text-gray-700 hover:bg-gray-100"
+                        class="block px-4 py-2 text-sm text-gray-700 hover:bg-gray-100 dark:text-gray-200 dark:hover:bg-gray-700"
                         on:click=move |_| set_is_open.set(false)
                     >
                         "Settings"
                     </a>
                     <button
-                        class="block w-full text-left px-4 py-2 text-sm text-gray-700 hover:bg-gray-100"
+                        class="block w-full text-left px-4 py-2 text-sm text-gray-700 hover:bg-gray-100 dark:text-gray-200 dark:hover:bg-gray-700"
                         on:click=move |_| {
                             user_ctx.logout();
                             use_navigate()("/", NavigateOptions::default());
@@ -277,33 +1006,45 @@ fn UserDropdown(#[prop(into)] user_name: String, #[prop(into)] avatar_url: Strin
     }
 }
 
+/// The logged-in user's name next to their avatar, which opens the user menu.
+#[component]
+fn CurrentUser() -> impl IntoView {
+    let user_ctx = expect_context::<UserContext>();
+    let user_resource = user_ctx.user();
+
+    view! {
+        {move || user_resource.get().as_deref().map(|user| match user {
+            Some(user) => view! {
+                <div class="flex items-center space-x-3">
+                    <span class="text-sm font-medium">{user.display_name().to_string()}</span>
+                    <UserDropdown
+                        user_name=user.login.clone()
+                        avatar_url=user.avatar_url.clone()
+                    />
+                </div>
+            }.into_any(),
+            None => view! { <div>"Loading..."</div> }.into_any(),
+        })}
+    }
+}
+
 #[component]
 fn MenuBar() -> impl IntoView {
     let user_ctx = expect_context::<UserContext>();
 
     view! {
-        <div class="bg-sky-700 text-white p-4 flex items-center justify-between">
+        <div class="bg-sky-700 text-white p-4 flex items-center justify-between dark:bg-sky-900">
             <div class="flex items-center space-x-4">
                 <h1 class="text-2xl font-bold">"Proof of Tests"</h1>
                 <div class="bg-sky-600 px-3 py-1 rounded-full text-sm">
                     "0 tests" // We'll make this dynamic later
                 </div>
             </div>
-            <div>
+            <div class="flex items-center space-x-4">
+                <ThemeToggle/>
                 {move || {
                     if user_ctx.is_logged_in() {
-                        let user_resource = user_ctx.user();
-                        view! {
-                            {move || user_resource.get().as_deref().map(|user| match user {
-                                Some(user) => view! {
-                                    <UserDropdown
-                                        user_name=user.login.clone()
-                                        avatar_url=user.avatar_url.clone()
-                                    />
-                                }.into_any(),
-                                None => view! { <div>"Loading..."</div> }.into_any(),
-                            })}
-                        }.into_any()
+                        view! { <CurrentUser/> }.into_any()
                     } else {
                         view! { <LoginButton /> }.into_any()
                     }
@@ -325,7 +1066,7 @@ pub fn shell(options: LeptosOptions) -> impl IntoView {
                 <HydrationScripts options/>
                 <MetaTags/>
             </head>
-            <body class="bg-sky-100">
+            <body>
                 <App/>
             </body>
         </html>
@@ -342,46 +1083,83 @@ pub fn App() -> impl IntoView {
     let user_ctx = UserContext::new();
     provide_context(user_ctx);
 
+    // The server doesn't know the preference, so pages render light and switch once hydrated.
+    let theme = RwSignal::new(Theme::default());
+    provide_context(theme);
+    Effect::new(move |_| theme.set(load_theme()));
+
     view! {
         <Stylesheet href="/style.css" />
         <Link rel="icon" type_="image/x-icon" href="/favicon.ico" />
+        <Body {..} class=move || theme.get().body_class() />
 
 
+        // Only visible while focused, the first tab stop on the page.
+        <a
+            href="#repositories"
+            class="sr-only focus:not-sr-only focus:absolute focus:top-2 focus:left-2 focus:z-50 focus:px-4 focus:py-2 focus:rounded focus:shadow focus:bg-white focus:text-sky-700"
+        >
+            "Skip to repositories"
+        </a>
+
         <Messages/>
 
         <MenuBar/>
 
-        <div class="bg-white" style:box-shadow="0 0px 5px rgba(0, 0, 0, 0.4)">
+        <div class="bg-white dark:bg-gray-800 dark:text-gray-100" style:box-shadow="0 0px 5px rgba(0, 0, 0, 0.4)">
             <div class="max-w-4xl mx-auto p-4">
                 <Router>
                     <main>
-                        <Routes fallback=|| "Not found">
-                            <Route
-                                path=path!("/")
-                                view=move || {
-                                    view! {
-                                        <div class="space-y-8">
-                                            <RepositoryList/>
-                                            <OrganizationList/>
-                                        </div>
+                        <ErrorBoundary fallback=|errors| view! {
+                            <div class="p-4 rounded-lg bg-red-100 text-red-800 dark:bg-red-900 dark:text-red-100" role="alert">
+                                <p class="font-medium">"Something went wrong"</p>
+                                <ul class="mt-2 list-disc list-inside text-sm">
+                                    {move || errors.get().into_iter().map(|(_, error)| {
+                                        view! { <li>{error.to_string()}</li> }
+                                    }).collect_view()}
+                                </ul>
+                            </div>
+                        }>
+                            <Routes fallback=|| "Not found">
+                                <Route
+                                    path=path!("/")
+                                    view=move || {
+                                        let user_ctx = expect_context::<UserContext>();
+                                        let visibility = RwSignal::new(Visibility::default());
+                                        let refresh = Refresh::new();
+                                        view! {
+                                            <div class="space-y-8">
+                                                <UploadWasm/>
+                                                <div class="flex items-center justify-between">
+                                                    <VisibilityFilter visibility/>
+                                                    <RefreshButton refresh/>
+                                                </div>
+                                                <RepositoryList visibility refresh/>
+                                                <Show when=move || user_ctx.has_scope("read:org")>
+                                                    <OrganizationList visibility refresh/>
+                                                </Show>
+                                            </div>
+                                        }
                                     }
-                                }
-                            />
-                            <Route
-                                path=path!("/settings")
-                                view=move || {
-                                    view! { <Settings/> }
-                                }
-                            />
-                            <Route
-                                path=path!("/oauth/callback")
-                                view=move || {
-                                    view! {
-                                        <OAuthCallback/>
+                                />
+                                <Route path=path!("/proofs/:wasm_hash/:proof_hash") view=ProofDetail />
+                                <Route path=path!("/my-proofs") view=MyProofs />
+                                <Route
+                                    path=path!("/settings")
+                                    view=move || {
+                                        view! { <Settings/> }
                                     }
-                                }
-                            />
-                        </Routes>
+                                />
+                                <Route
+                                    path=path!("/oauth/callback")
+                                    view=move || {
+                                        view! {
+                                            <OAuthCallback/>
+                                        }
+                                    }
+                                />
+                            </Routes>
+                        </ErrorBoundary>
                     </main>
                 </Router>
             </div>
@@ -392,6 +1170,18 @@ pub fn App() -> impl IntoView {
 #[derive(Params, Clone, Debug, PartialEq, Eq)]
 struct OAuthCallbackParams {
     code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// What to tell the user when GitHub redirects back with an `error` instead of a code.
+fn oauth_error_message(error: &str, description: Option<&str>) -> String {
+    match (error, description) {
+        ("access_denied", _) => "Login cancelled, GitHub access was not granted".to_string(),
+        (_, Some(description)) if !description.trim().is_empty() => format!("Failed to login: {}", description.trim()),
+        (error, _) => format!("Failed to login: {}", error),
+    }
 }
 
 #[component]
@@ -400,22 +1190,49 @@ fn OAuthCallback() -> impl IntoView {
     let params = use_query::<OAuthCallbackParams>();
     let message_ctx = expect_context::<MessageContext>();
     let user_ctx = expect_context::<UserContext>();
+    // GitHub accepts a code only once, so a rerun of the effect must not exchange it again.
+    let handled = StoredValue::new(false);
 
     Effect::new(move |_| {
         let navigate = navigate.clone();
         let message_ctx = message_ctx.clone();
         let user_ctx = user_ctx.clone();
 
-        if let Ok(OAuthCallbackParams { code: Some(code) }) = params.get() {
+        let Ok(params) = params.get() else {
+            return;
+        };
+        if handled.get_value() || (params.code.is_none() && params.error.is_none()) {
+            return;
+        }
+        handled.set_value(true);
+
+        // Cancelling on GitHub's consent screen comes back as `error=access_denied`
+        if let Some(error) = params.error {
+            take_oauth_state();
+            message_ctx.add(
+                oauth_error_message(&error, params.error_description.as_deref()),
+                MessageSeverity::Error,
+            );
+            navigate("/", NavigateOptions::default());
+            return;
+        }
+
+        if let Some(code) = params.code {
+            if let Err(reason) = verify_oauth_state(take_oauth_state().as_deref(), params.state.as_deref(), unix_time())
+            {
+                message_ctx.add(format!("Failed to login: {}", reason), MessageSeverity::Error);
+                navigate("/", NavigateOptions::default());
+                return;
+            }
             spawn_local(async move {
                 match exchange_token(code).await {
-                    Ok(token) => {
-                        user_ctx.login(token);
-                        message_ctx.add("Successfully logged in!", MessageSeverity::Info);
+                    Ok(granted) => {
+                        user_ctx.login(granted);
+                        message_ctx.add("Successfully logged in!", MessageSeverity::Success);
                         navigate("/", NavigateOptions::default());
                     }
                     Err(e) => {
-                        message_ctx.add(format!("Failed to login: {}", e), MessageSeverity::Error);
+                        report_server_error(&message_ctx, "login", e);
                         navigate("/", NavigateOptions::default());
                     }
                 }
@@ -430,18 +1247,237 @@ fn OAuthCallback() -> impl IntoView {
     }
 }
 
-fn get_access_token_from_storage() -> Option<UserAccessToken> {
-    use_context::<UserContext>()
-        .and_then(|ctx| ctx.get_token())
-        .map(UserAccessToken::from_string)
-}
-
 #[component]
 fn Settings() -> impl IntoView {
     view! {
         <div class="space-y-4">
             <h2 class="text-2xl font-bold">"Settings"</h2>
-            <p class="text-gray-600">"Settings page coming soon..."</p>
+            <p class="text-gray-600 dark:text-gray-400">"Settings page coming soon..."</p>
         </div>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh state that matches is accepted
+    #[test]
+    fn oauth_state_unit_test_1() {
+        let now = Duration::from_secs(1_700_000_000);
+        let stored = format!("abc123:{}", now.as_millis());
+        assert_eq!(verify_oauth_state(Some(&stored), Some("abc123"), now), Ok(()));
+        assert_eq!(
+            verify_oauth_state(Some(&stored), Some("abc123"), now + OAUTH_STATE_MAX_AGE),
+            Ok(())
+        );
+    }
+
+    // A state that doesn't match, or is missing on either side, is rejected
+    #[test]
+    fn oauth_state_unit_test_2() {
+        let now = Duration::from_secs(1_700_000_000);
+        let stored = format!("abc123:{}", now.as_millis());
+        assert!(verify_oauth_state(Some(&stored), Some("abc124"), now).is_err());
+        assert!(verify_oauth_state(Some(&stored), None, now).is_err());
+        assert!(verify_oauth_state(None, Some("abc123"), now).is_err());
+        assert!(verify_oauth_state(Some(":0"), Some(""), now).is_err());
+        assert!(verify_oauth_state(Some("abc123"), Some("abc123"), now).is_err());
+    }
+
+    // A state older than ten minutes is rejected even if it matches
+    #[test]
+    fn oauth_state_unit_test_3() {
+        let now = Duration::from_secs(1_700_000_000);
+        let stored = format!("abc123:{}", now.as_millis());
+        let later = now + OAUTH_STATE_MAX_AGE + Duration::from_millis(1);
+        assert_eq!(
+            verify_oauth_state(Some(&stored), Some("abc123"), later),
+            Err("login attempt expired")
+        );
+    }
+
+    // Repositories are filtered by full name, ignoring case, and an empty query keeps everything
+    #[test]
+    fn repository_filter_unit_test_1() {
+        let repo = Repository {
+            name: "pot-web".into(),
+            full_name: "proof-of-tests/pot-web".into(),
+            html_url: "https://github.com/proof-of-tests/pot-web".into(),
+            private: false,
+            description: None,
+            pushed_at: None,
+            updated_at: None,
+        };
+        assert!(repository_matches(&repo, ""));
+        assert!(repository_matches(&repo, "POT-WEB"));
+        assert!(repository_matches(&repo, "proof-of-tests/"));
+        assert!(!repository_matches(&repo, "pot-cli"));
+    }
+
+    // The visibility filter keeps everything by default and splits on the `private` flag otherwise
+    #[test]
+    fn visibility_filter_unit_test_1() {
+        let repo = |private| Repository {
+            name: "pot-web".into(),
+            full_name: "proof-of-tests/pot-web".into(),
+            html_url: "https://github.com/proof-of-tests/pot-web".into(),
+            private,
+            description: None,
+            pushed_at: None,
+            updated_at: None,
+        };
+        assert_eq!(Visibility::default(), Visibility::All);
+        assert!(Visibility::All.allows(&repo(false)) && Visibility::All.allows(&repo(true)));
+        assert!(Visibility::Public.allows(&repo(false)) && !Visibility::Public.allows(&repo(true)));
+        assert!(!Visibility::Private.allows(&repo(false)) && Visibility::Private.allows(&repo(true)));
+    }
+
+    // The callback route hangs off the base URL, whether or not it ends in a slash
+    #[test]
+    fn oauth_redirect_uri_unit_test_1() {
+        assert_eq!(
+            oauth_redirect_uri("https://proof-of-tests.org"),
+            "https://proof-of-tests.org/oauth/callback"
+        );
+        assert_eq!(
+            oauth_redirect_uri("http://localhost:8787/"),
+            "http://localhost:8787/oauth/callback"
+        );
+    }
+
+    // The cached organizations are served for five minutes after they were fetched
+    #[test]
+    fn org_cache_unit_test_1() {
+        let fetched_at = Duration::from_secs(1_700_000_000);
+        let cache = OrgCache {
+            user: "octocat".into(),
+            fetched_at_ms: fetched_at.as_millis() as u64,
+            orgs: vec![(
+                Organization {
+                    login: "proof-of-tests".into(),
+                    avatar_url: "https://avatars.githubusercontent.com/u/1".into(),
+                },
+                vec![],
+            )],
+        };
+        assert!(cache.is_fresh(fetched_at));
+        assert!(cache.is_fresh(fetched_at + ORG_CACHE_TTL - Duration::from_millis(1)));
+        assert!(!cache.is_fresh(fetched_at + ORG_CACHE_TTL));
+
+        let round_trip: OrgCache = serde_json::from_str(&serde_json::to_string(&cache).unwrap()).unwrap();
+        assert_eq!(round_trip.user, "octocat");
+        assert_eq!(round_trip.orgs[0].0, cache.orgs[0].0);
+    }
+
+    // Recently updated puts the latest push or update first and never-pushed repositories last, name order ignores case
+    #[test]
+    fn repository_order_unit_test_1() {
+        let repo = |full_name: &str, pushed_at: Option<&str>, updated_at: Option<&str>| Repository {
+            name: full_name.rsplit('/').next().unwrap().into(),
+            full_name: full_name.into(),
+            html_url: format!("https://github.com/{}", full_name),
+            private: false,
+            description: None,
+            pushed_at: pushed_at.map(|time| time.parse().unwrap()),
+            updated_at: updated_at.map(|time| time.parse().unwrap()),
+        };
+        let mut repos = vec![
+            repo("proof-of-tests/empty", None, Some("2024-01-01T00:00:00Z")),
+            repo(
+                "proof-of-tests/pot-web",
+                Some("2024-11-02T10:00:00Z"),
+                Some("2024-11-01T00:00:00Z"),
+            ),
+            repo("Proof-of-tests/Archive", None, None),
+            repo(
+                "proof-of-tests/pot-cli",
+                Some("2024-06-01T00:00:00Z"),
+                Some("2024-12-24T08:30:00Z"),
+            ),
+        ];
+        let names = |repos: &[Repository]| repos.iter().map(|repo| repo.name.clone()).collect::<Vec<_>>();
+
+        repos.sort_by(|a, b| RepositoryOrder::RecentlyUpdated.compare(a, b));
+        assert_eq!(names(&repos), ["pot-cli", "pot-web", "empty", "Archive"]);
+
+        repos.sort_by(|a, b| RepositoryOrder::Name.compare(a, b));
+        assert_eq!(names(&repos), ["Archive", "empty", "pot-cli", "pot-web"]);
+        assert_eq!(RepositoryOrder::default(), RepositoryOrder::RecentlyUpdated);
+    }
+
+    // Pages hold 25 repositories, the last one may be short, and pages past the end show the last page
+    #[test]
+    fn page_range_unit_test_1() {
+        assert_eq!(page_range(0, 0), 0..0);
+        assert_eq!(page_range(10, 0), 0..10);
+        assert_eq!(page_range(60, 0), 0..25);
+        assert_eq!(page_range(60, 1), 25..50);
+        assert_eq!(page_range(60, 2), 50..60);
+        assert_eq!(page_range(60, 7), 50..60);
+        assert_eq!(page_range(50, 2), 25..50);
+    }
+
+    // A cancelled login gets its own message, other errors show GitHub's description or the error code
+    #[test]
+    fn oauth_error_message_unit_test_1() {
+        assert_eq!(
+            oauth_error_message("access_denied", Some("The user has denied your application access.")),
+            "Login cancelled, GitHub access was not granted"
+        );
+        assert_eq!(
+            oauth_error_message(
+                "redirect_uri_mismatch",
+                Some("The redirect_uri MUST match the registered callback URL for this application.")
+            ),
+            "Failed to login: The redirect_uri MUST match the registered callback URL for this application."
+        );
+        assert_eq!(
+            oauth_error_message("application_suspended", None),
+            "Failed to login: application_suspended"
+        );
+    }
+
+    // A fetch counts as in flight until it finishes or its future is dropped halfway
+    #[test]
+    fn refresh_unit_test_1() {
+        Owner::new().with(|| {
+            use futures_util::FutureExt;
+
+            let refresh = Refresh::new();
+            assert_eq!(refresh.run(async { 7 }).now_or_never(), Some(7));
+            assert_eq!(refresh.in_flight.get_untracked(), 0);
+
+            let mut pending = Box::pin(refresh.run(futures_util::future::pending::<()>()));
+            assert!((&mut pending).now_or_never().is_none());
+            assert_eq!(refresh.in_flight.get_untracked(), 1);
+            drop(pending);
+            assert_eq!(refresh.in_flight.get_untracked(), 0);
+        });
+    }
+
+    // A stored choice wins over the system preference, which only applies when nothing valid is stored
+    #[test]
+    fn theme_unit_test_1() {
+        assert_eq!(Theme::initial(Some("light"), true), Theme::Light);
+        assert_eq!(Theme::initial(Some("dark"), false), Theme::Dark);
+        assert_eq!(Theme::initial(None, true), Theme::Dark);
+        assert_eq!(Theme::initial(None, false), Theme::Light);
+        assert_eq!(Theme::initial(Some("sepia"), true), Theme::Dark);
+        assert_eq!(Theme::Dark.toggled().as_str(), "light");
+        assert!(Theme::Dark.body_class().split(' ').any(|class| class == "dark"));
+    }
+
+    // Empty and blank tokens count as no token, others lose their surrounding whitespace
+    #[test]
+    fn usable_token_unit_test_1() {
+        assert_eq!(usable_token(None), None);
+        assert_eq!(usable_token(Some(String::new())), None);
+        assert_eq!(usable_token(Some(" \n\t".to_string())), None);
+        assert_eq!(usable_token(Some("gho_abc".to_string())), Some("gho_abc".to_string()));
+        assert_eq!(
+            usable_token(Some(" gho_abc\n".to_string())),
+            Some("gho_abc".to_string())
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Browser-side helpers for the worker's own HTTP API.
+
+use std::time::Duration;
+
+use leptos::prelude::window;
+
+use crate::api::{ErrorResponse, ProofState, ProofStatusResponse};
+use crate::components::{MessageContext, MessageSeverity};
+use crate::github::backoff_delay;
+
+/// How many times a proof's status is checked before giving up on it leaving `pending`.
+pub const PROOF_STATUS_POLLS: u32 = 6;
+
+/// Delay before the second check, doubling with every check after it.
+pub const PROOF_STATUS_DELAY: Duration = Duration::from_millis(500);
+
+// The reason a request to the worker failed, from the JSON error body when there is one.
+async fn error_text(response: reqwest::Response) -> String {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let error = serde_json::from_str::<ErrorResponse>(&body).map_or(body, |e| e.error);
+    format!("{} {}", status, error)
+}
+
+pub async fn fetch_proof_status(wasm_hash: &str, proof_hash: &str) -> Result<ProofStatusResponse, String> {
+    let origin = window().location().origin().map_err(|e| format!("{:?}", e))?;
+    let response = reqwest::get(format!("{}/proofs/{}/{}/status", origin, wasm_hash, proof_hash))
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(error_text(response).await);
+    }
+    response.json::<ProofStatusResponse>().await.map_err(|e| e.to_string())
+}
+
+/// Posts a proof to `/upload_proof` as the multipart form it takes, owned by whoever `token` belongs to. Returns the
+/// hash the proof is stored under.
+pub async fn submit_proof(fields: Vec<(&'static str, String)>, token: Option<String>) -> Result<String, String> {
+    let origin = window().location().origin().map_err(|e| format!("{:?}", e))?;
+    let form = fields
+        .into_iter()
+        .fold(reqwest::multipart::Form::new(), |form, (name, value)| {
+            form.text(name, value)
+        });
+    let mut request = reqwest::Client::new()
+        .post(format!("{}/upload_proof", origin))
+        .multipart(form);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(error_text(response).await);
+    }
+    response.text().await.map_err(|e| e.to_string())
+}
+
+/// Polls a proof's status with exponential backoff until it is verified or rejected, and reports the outcome as a
+/// message. `/upload_proof` stores the verdict before it answers, so after a successful upload the first check
+/// settles it. A proof only reads as `pending` while it isn't stored yet.
+pub async fn watch_proof_status(wasm_hash: &str, proof_hash: &str, message_ctx: &MessageContext) {
+    for poll in 0..PROOF_STATUS_POLLS {
+        if poll > 0 {
+            gloo_timers::future::sleep(backoff_delay(PROOF_STATUS_DELAY, poll - 1)).await;
+        }
+        match fetch_proof_status(wasm_hash, proof_hash).await {
+            Ok(ProofStatusResponse { state, .. }) if !state.is_terminal() => {}
+            Ok(ProofStatusResponse {
+                state: ProofState::Verified,
+                result,
+            }) => {
+                let result = result
+                    .map(|result| format!(" with hash {}", result))
+                    .unwrap_or_default();
+                message_ctx.add(
+                    format!("Proof {} verified{}", proof_hash, result),
+                    MessageSeverity::Success,
+                );
+                return;
+            }
+            Ok(_) => {
+                message_ctx.add(format!("Proof {} was rejected", proof_hash), MessageSeverity::Error);
+                return;
+            }
+            Err(e) => {
+                message_ctx.add(
+                    format!("Failed to check proof {}: {}", proof_hash, e),
+                    MessageSeverity::Error,
+                );
+                return;
+            }
+        }
+    }
+    message_ctx.add(
+        format!("Proof {} is still pending verification", proof_hash),
+        MessageSeverity::Warn,
+    );
+}
@@ -1,7 +1,38 @@
 use leptos::prelude::*;
+use std::time::Duration;
 
-#[derive(Clone, Debug)]
+/// How long a `Success` or `Info` message stays on screen before dismissing itself.
+const INFO_TTL: Duration = Duration::from_secs(5);
+
+/// Messages shown at most at once, older ones are dropped first.
+const DEFAULT_MAX_MESSAGES: usize = 5;
+
+/// How often the relative timestamps on messages are brought up to date.
+const RELATIVE_TIME_REFRESH: Duration = Duration::from_secs(15);
+
+fn now_millis() -> u64 {
+    instant::SystemTime::now()
+        .duration_since(instant::SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// How long ago `then` was as seen from `now`, both in milliseconds since the Unix epoch: "just now", "42s ago",
+/// "5m ago", "3h ago" or "2d ago".
+pub fn format_relative(now: u64, then: u64) -> String {
+    let seconds = now.saturating_sub(then) / 1000;
+    match seconds {
+        0..10 => "just now".to_string(),
+        10..60 => format!("{}s ago", seconds),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86400 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum MessageSeverity {
+    Success,
     Info,
     Warn,
     Error,
@@ -12,50 +43,102 @@ pub struct Message {
     id: u32,
     text: String,
     severity: MessageSeverity,
+    /// When the message was last added, in milliseconds since the Unix epoch.
+    created_at: u64,
+    /// How many times the message was added in a row.
+    count: u32,
+}
+
+impl Message {
+    /// The text as shown, with the repeat count once the message was added more than once.
+    fn display_text(&self) -> String {
+        if self.count > 1 {
+            format!("{} (×{})", self.text, self.count)
+        } else {
+            self.text.clone()
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct MessageContext {
     messages: RwSignal<Vec<Message>>,
     next_id: RwSignal<u32>,
+    max_messages: usize,
+}
+
+impl Default for MessageContext {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MessageContext {
     pub fn new() -> Self {
         Self {
-            // messages: create_rw_signal(Vec::new()),
-            messages: RwSignal::new(vec![
-                Message {
-                    id: 0,
-                    text: "Welcome to Proof of Tests!".into(),
-                    severity: MessageSeverity::Info,
-                },
-                Message {
-                    id: 1,
-                    text: "Some features may be under development".into(),
-                    severity: MessageSeverity::Warn,
-                },
-                Message {
-                    id: 2,
-                    text: "Unable to connect to server".into(),
-                    severity: MessageSeverity::Error,
-                },
-            ]),
-            next_id: RwSignal::new(3),
+            messages: RwSignal::new(Vec::new()),
+            next_id: RwSignal::new(0),
+            max_messages: DEFAULT_MAX_MESSAGES,
         }
     }
 
+    /// A context seeded with one message of each severity, for trying out the overlay.
+    #[cfg(test)]
+    pub fn with_demo_messages() -> Self {
+        let ctx = Self::new();
+        ctx.add_with_ttl("Welcome to Proof of Tests!", MessageSeverity::Info, None);
+        ctx.add("Some features may be under development", MessageSeverity::Warn);
+        ctx.add("Unable to connect to server", MessageSeverity::Error);
+        ctx
+    }
+
+    /// Adds a message. `Success` and `Info` messages dismiss themselves after a few seconds, anything more severe stays
+    /// until the user dismisses it.
     pub fn add(&self, text: impl Into<String>, severity: MessageSeverity) {
+        let ttl = match severity {
+            MessageSeverity::Success | MessageSeverity::Info => Some(INFO_TTL),
+            MessageSeverity::Warn | MessageSeverity::Error => None,
+        };
+        self.add_with_ttl(text, severity, ttl);
+    }
+
+    /// Adds a message that is removed after `ttl`, or kept until dismissed if there is none. Adding the same message
+    /// as the newest one on screen bumps its count instead of stacking a copy.
+    pub fn add_with_ttl(&self, text: impl Into<String>, severity: MessageSeverity, ttl: Option<Duration>) {
         let id = self.next_id.get();
         self.next_id.set(id + 1);
+        let text = text.into();
 
         self.messages.update(|messages| {
-            messages.push(Message {
-                id,
-                text: text.into(),
-                severity,
-            });
+            match messages.last_mut() {
+                // The repeat takes a fresh id, so a pending timeout for the earlier copy leaves it alone.
+                Some(last) if last.text == text && last.severity == severity => {
+                    last.id = id;
+                    last.count += 1;
+                    last.created_at = now_millis();
+                }
+                _ => messages.push(Message {
+                    id,
+                    text,
+                    severity,
+                    created_at: now_millis(),
+                    count: 1,
+                }),
+            }
+            let excess = messages.len().saturating_sub(self.max_messages);
+            messages.drain(..excess);
         });
+
+        if let Some(ttl) = ttl {
+            let messages = self.messages;
+            // The message may have been dismissed already, or the context dropped, by the time this fires.
+            set_timeout(
+                move || {
+                    let _ = messages.try_update(|messages| messages.retain(|msg| msg.id != id));
+                },
+                ttl,
+            );
+        }
     }
 
     pub fn remove(&self, id: u32) {
@@ -63,22 +146,67 @@ impl MessageContext {
             messages.retain(|msg| msg.id != id);
         });
     }
+
+    /// Dismisses the most recently added message still on screen.
+    pub fn remove_latest(&self) {
+        self.messages.update(|messages| {
+            messages.pop();
+        });
+    }
+}
+
+/// What went wrong in a server function call, in words for a message. A [`ServerFnError::ServerError`] is the reason
+/// the server function gave, without the prefix its `Display` adds.
+pub fn server_error_text(err: &ServerFnError) -> String {
+    match err {
+        ServerFnError::ServerError(reason) => reason.clone(),
+        err => err.to_string(),
+    }
+}
+
+/// Shows a failed server function call as an `Error` message, "Failed to {action}: {reason}".
+pub fn report_server_error(ctx: &MessageContext, action: &str, err: ServerFnError) {
+    ctx.add(
+        format!("Failed to {}: {}", action, server_error_text(&err)),
+        MessageSeverity::Error,
+    );
 }
 
 #[component]
 pub fn Messages() -> impl IntoView {
     let message_ctx = expect_context::<MessageContext>();
+    let now = RwSignal::new(now_millis());
+    // Effects only run in the browser, so the server render never starts a timer.
+    Effect::new(move |_| {
+        if let Ok(handle) = set_interval_with_handle(move || now.set(now_millis()), RELATIVE_TIME_REFRESH) {
+            on_cleanup(move || handle.clear());
+        }
+    });
+    let on_keydown = {
+        let message_ctx = message_ctx.clone();
+        move |ev: web_sys::KeyboardEvent| {
+            if ev.key() == "Escape" {
+                message_ctx.remove_latest();
+            }
+        }
+    };
 
     view! {
-        <div class="fixed top-4 left-1/2 -translate-x-1/2 z-50 space-y-2 max-w-2xl w-full px-4">
+        <div
+            class="fixed top-4 left-1/2 -translate-x-1/2 z-50 space-y-2 max-w-2xl w-full px-4"
+            aria-live="polite"
+            on:keydown=on_keydown
+        >
             {move || message_ctx.messages.get().into_iter().map(|message| {
                 let message_ctx = message_ctx.clone();
                 let id = message.id;
+                let created_at = message.created_at;
 
                 let bg_color = match message.severity {
-                    MessageSeverity::Info => "bg-blue-100 text-blue-800",
-                    MessageSeverity::Warn => "bg-yellow-100 text-yellow-800",
-                    MessageSeverity::Error => "bg-red-100 text-red-800",
+                    MessageSeverity::Success => "bg-green-100 text-green-800 dark:bg-green-900 dark:text-green-100",
+                    MessageSeverity::Info => "bg-blue-100 text-blue-800 dark:bg-blue-900 dark:text-blue-100",
+                    MessageSeverity::Warn => "bg-yellow-100 text-yellow-800 dark:bg-yellow-900 dark:text-yellow-100",
+                    MessageSeverity::Error => "bg-red-100 text-red-800 dark:bg-red-900 dark:text-red-100",
                 };
 
                 view! {
@@ -86,9 +214,14 @@ pub fn Messages() -> impl IntoView {
                         class=format!("p-4 rounded-lg shadow-md flex justify-between items-start {}", bg_color)
                         role="alert"
                     >
-                        <span>{message.text}</span>
+                        <span>{message.display_text()}</span>
+                        // Hidden from screen readers, which would otherwise announce the message again on every tick.
+                        <span class="ml-auto pl-4 text-xs opacity-70 whitespace-nowrap" aria-hidden="true">
+                            {move || format_relative(now.get(), created_at)}
+                        </span>
                         <button
-                            class="ml-4 hover:opacity-70"
+                            class="ml-4 -my-1 px-2 py-1 text-lg leading-none rounded hover:opacity-70"
+                            aria-label="Dismiss message"
                             on:click=move |_| message_ctx.remove(id)
                         >
                             "×"
@@ -99,3 +232,157 @@ pub fn Messages() -> impl IntoView {
         </div>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Only the most recent `max_messages` messages are kept, with the seeded ones dropped first
+    #[test]
+    fn message_cap_unit_test_1() {
+        Owner::new().with(|| {
+            let ctx = MessageContext::with_demo_messages();
+            assert_eq!(ctx.messages.get_untracked().len(), 3);
+            for i in 0..10 {
+                ctx.add(format!("message {}", i), MessageSeverity::Warn);
+            }
+
+            let texts = ctx
+                .messages
+                .get_untracked()
+                .into_iter()
+                .map(|message| message.text)
+                .collect::<Vec<_>>();
+            assert_eq!(ctx.max_messages, DEFAULT_MAX_MESSAGES);
+            assert_eq!(texts, ["message 5", "message 6", "message 7", "message 8", "message 9"]);
+        });
+    }
+
+    // A fresh context starts without any messages
+    #[test]
+    fn message_cap_unit_test_2() {
+        Owner::new().with(|| {
+            assert!(MessageContext::new().messages.get_untracked().is_empty());
+        });
+    }
+
+    // Escape dismisses the newest message first
+    #[test]
+    fn remove_latest_unit_test_1() {
+        Owner::new().with(|| {
+            let ctx = MessageContext::with_demo_messages();
+            ctx.remove_latest();
+            let texts = ctx
+                .messages
+                .get_untracked()
+                .into_iter()
+                .map(|message| message.text)
+                .collect::<Vec<_>>();
+            assert_eq!(
+                texts,
+                ["Welcome to Proof of Tests!", "Some features may be under development"]
+            );
+
+            let empty = MessageContext::new();
+            empty.remove_latest();
+            assert!(empty.messages.get_untracked().is_empty());
+        });
+    }
+
+    // Anything under ten seconds is "just now", including clocks that went backwards
+    #[test]
+    fn format_relative_unit_test_1() {
+        let now = 1_700_000_000_000;
+        assert_eq!(format_relative(now, now), "just now");
+        assert_eq!(format_relative(now, now - 9_999), "just now");
+        assert_eq!(format_relative(now, now + 5_000), "just now");
+        assert_eq!(format_relative(now, now - 10_000), "10s ago");
+        assert_eq!(format_relative(now, now - 59_999), "59s ago");
+    }
+
+    // Minutes, hours and days each start at their boundary
+    #[test]
+    fn format_relative_unit_test_2() {
+        let now = 1_700_000_000_000;
+        assert_eq!(format_relative(now, now - 60_000), "1m ago");
+        assert_eq!(format_relative(now, now - 3_599_999), "59m ago");
+        assert_eq!(format_relative(now, now - 3_600_000), "1h ago");
+        assert_eq!(format_relative(now, now - 86_399_999), "23h ago");
+        assert_eq!(format_relative(now, now - 86_400_000), "1d ago");
+        assert_eq!(format_relative(now, now - 3 * 86_400_000), "3d ago");
+    }
+
+    // The reason given by a server function is shown as is, other failures keep their description
+    #[test]
+    fn server_error_text_unit_test_1() {
+        Owner::new().with(|| {
+            assert_eq!(
+                server_error_text(&ServerFnError::ServerError("bad_verification_code".to_string())),
+                "bad_verification_code"
+            );
+            let request = ServerFnError::Request("connection refused".to_string());
+            assert_eq!(server_error_text(&request), request.to_string());
+
+            let ctx = MessageContext::new();
+            report_server_error(
+                &ctx,
+                "login",
+                ServerFnError::ServerError("GitHub answered 502".to_string()),
+            );
+            let messages = ctx.messages.get_untracked();
+            assert_eq!(messages[0].text, "Failed to login: GitHub answered 502");
+            assert_eq!(messages[0].severity, MessageSeverity::Error);
+        });
+    }
+
+    // Repeating the newest message bumps its count, anything in between starts a new one
+    #[test]
+    fn message_repeat_unit_test_1() {
+        Owner::new().with(|| {
+            let ctx = MessageContext::new();
+            for _ in 0..3 {
+                ctx.add("Failed to load repositories", MessageSeverity::Error);
+            }
+            ctx.add("Failed to load repositories", MessageSeverity::Warn);
+            ctx.add("Only 3 repositories could be loaded", MessageSeverity::Warn);
+            ctx.add("Failed to load repositories", MessageSeverity::Warn);
+
+            let messages = ctx.messages.get_untracked();
+            let counts = messages.iter().map(|message| message.count).collect::<Vec<_>>();
+            assert_eq!(counts, [3, 1, 1, 1]);
+            assert_eq!(messages[0].id, 2);
+        });
+    }
+
+    // Dismissing a repeated message forgets its count
+    #[test]
+    fn message_repeat_unit_test_2() {
+        Owner::new().with(|| {
+            let ctx = MessageContext::new();
+            ctx.add("Unable to connect to server", MessageSeverity::Error);
+            ctx.add("Unable to connect to server", MessageSeverity::Error);
+            ctx.remove_latest();
+            ctx.add("Unable to connect to server", MessageSeverity::Error);
+            assert_eq!(ctx.messages.get_untracked()[0].count, 1);
+        });
+    }
+
+    // The count only shows up once a message repeats
+    #[test]
+    fn message_repeat_unit_test_3() {
+        Owner::new().with(|| {
+            let ctx = MessageContext::new();
+            ctx.add("Unable to connect to server", MessageSeverity::Error);
+            assert_eq!(
+                ctx.messages.get_untracked()[0].display_text(),
+                "Unable to connect to server"
+            );
+            ctx.add("Unable to connect to server", MessageSeverity::Error);
+            ctx.add("Unable to connect to server", MessageSeverity::Error);
+            assert_eq!(
+                ctx.messages.get_untracked()[0].display_text(),
+                "Unable to connect to server (×3)"
+            );
+        });
+    }
+}
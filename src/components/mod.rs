@@ -1,3 +1,11 @@
 mod messages;
+mod proofs;
+mod upload;
 
-pub use messages::{MessageContext, MessageSeverity, Messages};
+pub use messages::{report_server_error, MessageContext, MessageSeverity, Messages};
+#[cfg(feature = "ssr")]
+pub use proofs::{GetProof, ListMyProofs, ListProofs};
+pub use proofs::{MyProofs, ProofDetail, ProofList};
+pub use upload::UploadWasm;
+#[cfg(feature = "ssr")]
+pub use upload::UploadWasmModule;
@@ -0,0 +1,330 @@
+use crate::app::UserContext;
+use crate::components::upload::copy_to_clipboard;
+use crate::components::{report_server_error, MessageContext, MessageSeverity};
+use crate::proof::{Proof, ProofOwner};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use leptos_router::hooks::use_params;
+use leptos_router::params::Params;
+#[cfg(feature = "ssr")]
+use std::sync::Arc;
+
+/// The first page of proofs stored for a module.
+#[server(ListProofs, "/api")]
+#[worker::send]
+pub async fn list_proofs(wasm_hash: String) -> Result<Vec<Proof>, ServerFnError> {
+    use crate::hash::is_sha256_hex;
+    use axum::Extension;
+    use leptos_axum::extract;
+    use worker::Env;
+
+    if !is_sha256_hex(&wasm_hash) {
+        if let Some(response) = use_context::<leptos_axum::ResponseOptions>() {
+            response.set_status(http::StatusCode::BAD_REQUEST);
+        }
+        return Err(ServerFnError::ServerError(format!(
+            "`{}` is not a module hash",
+            wasm_hash
+        )));
+    }
+    let Extension(env): Extension<Arc<Env>> = extract().await?;
+    let page = crate::handlers::list_proofs_page(&crate::handlers::proofs_bucket(&env)?, &wasm_hash, None, None)
+        .await
+        .map_err(|e| ServerFnError::new(format!("{:#}", e)))?;
+    Ok(page.proofs)
+}
+
+/// The proof stored under `proof_hash` for the module `wasm_hash`, `None` when there is no such proof.
+#[server(GetProof, "/api")]
+#[worker::send]
+pub async fn get_proof(wasm_hash: String, proof_hash: String) -> Result<Option<Proof>, ServerFnError> {
+    use crate::hash::is_sha256_hex;
+    use axum::Extension;
+    use leptos_axum::extract;
+    use worker::Env;
+
+    if !is_sha256_hex(&wasm_hash) || !is_sha256_hex(&proof_hash) {
+        return Ok(None);
+    }
+    let Extension(env): Extension<Arc<Env>> = extract().await?;
+    crate::handlers::find_proof(&crate::handlers::proofs_bucket(&env)?, &wasm_hash, &proof_hash)
+        .await
+        .map_err(|e| ServerFnError::new(format!("{:#}", e)))
+}
+
+/// Page of a single proof, see [`ProofDetail`].
+pub fn proof_permalink(proof: &Proof) -> String {
+    format!("/proofs/{}/{}", proof.wasm_hash, proof.content_hash())
+}
+
+fn owner_label(owner: &ProofOwner) -> String {
+    match owner {
+        ProofOwner::Anonymous => "Anonymous".to_string(),
+        ProofOwner::GithubId(id) => format!("GitHub #{}", id),
+    }
+}
+
+// Milliseconds since the Unix epoch as a UTC timestamp, or the raw number if it is out of range.
+fn format_created_at(millis: u64) -> String {
+    i64::try_from(millis)
+        .ok()
+        .and_then(chrono::DateTime::from_timestamp_millis)
+        .map_or_else(
+            || millis.to_string(),
+            |time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        )
+}
+
+#[derive(Params, Clone, Debug, PartialEq, Eq)]
+struct ProofParams {
+    wasm_hash: Option<String>,
+    proof_hash: Option<String>,
+}
+
+/// A single proof at `/proofs/:wasm_hash/:proof_hash`, the address to share it by.
+#[component]
+pub fn ProofDetail() -> impl IntoView {
+    let message_ctx = expect_context::<MessageContext>();
+    let params = use_params::<ProofParams>();
+
+    let proof = LocalResource::new({
+        let message_ctx = message_ctx.clone();
+        move || {
+            let params = params.get();
+            let message_ctx = message_ctx.clone();
+            async move {
+                let Ok(ProofParams {
+                    wasm_hash: Some(wasm_hash),
+                    proof_hash: Some(proof_hash),
+                }) = params
+                else {
+                    return None;
+                };
+                match get_proof(wasm_hash, proof_hash).await {
+                    Ok(proof) => proof,
+                    Err(e) => {
+                        report_server_error(&message_ctx, "load proof", e);
+                        None
+                    }
+                }
+            }
+        }
+    });
+
+    let copy_permalink = move |_| {
+        let message_ctx = message_ctx.clone();
+        let url = window().location().href().unwrap_or_default();
+        spawn_local(async move {
+            match copy_to_clipboard(&url).await {
+                Ok(()) => message_ctx.add("Permalink copied to clipboard", MessageSeverity::Success),
+                Err(_) => message_ctx.add(
+                    format!("Couldn't access the clipboard, the link is {}", url),
+                    MessageSeverity::Info,
+                ),
+            }
+        });
+    };
+
+    view! {
+        <Suspense fallback=move || view! { <p class="text-gray-500">"Loading proof..."</p> }>
+            {move || {
+                let copy_permalink = copy_permalink.clone();
+                Suspend::new(async move {
+                    let Some(proof) = proof.await else {
+                        return view! {
+                            <div class="py-8 text-center space-y-2">
+                                <h2 class="text-2xl font-bold">"Proof not found"</h2>
+                                <p class="text-gray-500 dark:text-gray-400">
+                                    "There is no proof at this address. Check the link, or upload the proof first."
+                                </p>
+                                <a href="/" class="text-sky-700 hover:underline dark:text-sky-400">"Back to the start page"</a>
+                            </div>
+                        }.into_any();
+                    };
+                    view! {
+                        <div class="space-y-4">
+                            <div class="flex items-center justify-between">
+                                <h2 class="text-2xl font-bold">"Proof"</h2>
+                                <button
+                                    class="px-3 py-1 text-sm border rounded hover:bg-gray-100 dark:border-gray-600 dark:hover:bg-gray-700"
+                                    on:click=copy_permalink
+                                >
+                                    "Copy permalink"
+                                </button>
+                            </div>
+                            <dl class="grid grid-cols-[max-content_1fr] gap-x-4 gap-y-2 text-sm">
+                                <dt class="text-gray-500">"Module"</dt>
+                                <dd><code class="break-all">{proof.wasm_hash.clone()}</code></dd>
+                                <dt class="text-gray-500">"State"</dt>
+                                <dd>{format!("{:?}", proof.state)}</dd>
+                                <dt class="text-gray-500">"Owner"</dt>
+                                <dd>{owner_label(&proof.owner)}</dd>
+                                <dt class="text-gray-500">"Seed"</dt>
+                                <dd><code>{proof.seed}</code></dd>
+                                <dt class="text-gray-500">"Result"</dt>
+                                <dd><code>{format!("{:016x}", proof.hash)}</code></dd>
+                                <dt class="text-gray-500">"Count"</dt>
+                                <dd>{proof.count}</dd>
+                                <dt class="text-gray-500">"Uploaded"</dt>
+                                <dd>{format_created_at(proof.created_at)}</dd>
+                            </dl>
+                        </div>
+                    }.into_any()
+                })
+            }}
+        </Suspense>
+    }
+}
+
+/// The proofs of the GitHub user `token` belongs to, across all modules.
+#[server(ListMyProofs, "/api")]
+#[worker::send]
+pub async fn list_my_proofs(token: String) -> Result<Vec<Proof>, ServerFnError> {
+    use crate::github::github_api_url;
+    use crate::handlers::{github_user_id, list_owner_proofs};
+    use axum::Extension;
+    use leptos_axum::extract;
+    use worker::Env;
+
+    let Extension(env): Extension<Arc<Env>> = extract().await?;
+    let id = github_user_id(github_api_url(), &token).await?;
+    list_owner_proofs(&crate::handlers::proofs_bucket(&env)?, id)
+        .await
+        .map_err(|e| ServerFnError::new(format!("{:#}", e)))
+}
+
+/// One row per proof, each linking to its permalink. `show_module` adds a column for the module, for listings that
+/// span several.
+#[component]
+fn ProofTable(proofs: Vec<Proof>, #[prop(optional)] show_module: bool) -> impl IntoView {
+    view! {
+        <table class="w-full text-sm">
+            <thead class="text-left text-gray-500">
+                <tr>
+                    <Show when=move || show_module>
+                        <th>"Module"</th>
+                    </Show>
+                    <th>"Seed"</th>
+                    <th>"Hash"</th>
+                    <th>"Count"</th>
+                    <th>"State"</th>
+                    <th>"Owner"</th>
+                    <th></th>
+                </tr>
+            </thead>
+            <tbody>
+                {proofs.into_iter().map(|proof| {
+                    let module = proof.wasm_hash.chars().take(12).collect::<String>();
+                    let wasm_hash = proof.wasm_hash.clone();
+                    view! {
+                        <tr class="border-t dark:border-gray-700">
+                            <Show when=move || show_module>
+                                <td><code title=wasm_hash.clone()>{module.clone()}</code></td>
+                            </Show>
+                            <td><code>{proof.seed}</code></td>
+                            <td><code>{format!("{:016x}", proof.hash)}</code></td>
+                            <td>{proof.count}</td>
+                            <td>{format!("{:?}", proof.state)}</td>
+                            <td>{owner_label(&proof.owner)}</td>
+                            <td>
+                                <a href=proof_permalink(&proof) class="text-sky-700 hover:underline dark:text-sky-400">
+                                    "Permalink"
+                                </a>
+                            </td>
+                        </tr>
+                    }
+                }).collect_view()}
+            </tbody>
+        </table>
+    }
+}
+
+/// The logged in user's proofs, at `/my-proofs`.
+#[component]
+pub fn MyProofs() -> impl IntoView {
+    let message_ctx = expect_context::<MessageContext>();
+    let user_ctx = expect_context::<UserContext>();
+
+    // Reading the token outside of the future reloads the list when the user logs in or out.
+    let proofs = LocalResource::new(move || {
+        let message_ctx = message_ctx.clone();
+        let token = user_ctx.get_token();
+        async move {
+            let token = token?;
+            match list_my_proofs(token).await {
+                Ok(proofs) => Some(proofs),
+                Err(e) => {
+                    report_server_error(&message_ctx, "load your proofs", e);
+                    Some(vec![])
+                }
+            }
+        }
+    });
+
+    view! {
+        <div class="space-y-4">
+            <h2 class="text-2xl font-bold">"My Proofs"</h2>
+            <Suspense fallback=move || view! { <p class="text-gray-500">"Loading proofs..."</p> }>
+                {move || Suspend::new(async move {
+                    match proofs.await {
+                        None => view! {
+                            <p class="text-gray-500 dark:text-gray-400">"Log in with GitHub to see the proofs you uploaded"</p>
+                        }.into_any(),
+                        Some(proofs) if proofs.is_empty() => view! {
+                            <p class="text-gray-500 dark:text-gray-400">"You haven't uploaded any proofs yet"</p>
+                        }.into_any(),
+                        Some(proofs) => view! { <ProofTable proofs show_module=true/> }.into_any(),
+                    }
+                })}
+            </Suspense>
+        </div>
+    }
+}
+
+#[component]
+pub fn ProofList(wasm_hash: String) -> impl IntoView {
+    let message_ctx = expect_context::<MessageContext>();
+
+    let proofs = LocalResource::new(move || {
+        let message_ctx = message_ctx.clone();
+        let wasm_hash = wasm_hash.clone();
+        async move {
+            match list_proofs(wasm_hash).await {
+                Ok(proofs) => proofs,
+                Err(e) => {
+                    report_server_error(&message_ctx, "load proofs", e);
+                    vec![]
+                }
+            }
+        }
+    });
+
+    view! {
+        <div class="space-y-2">
+            <h3 class="text-xl font-semibold">"Proofs"</h3>
+            <Suspense fallback=move || view! { <p class="text-gray-500">"Loading proofs..."</p> }>
+                {move || Suspend::new(async move {
+                    let proofs = proofs.await;
+                    if proofs.is_empty() {
+                        view! { <p class="text-gray-500">"No proofs for this module yet"</p> }.into_any()
+                    } else {
+                        view! { <ProofTable proofs/> }.into_any()
+                    }
+                })}
+            </Suspense>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Upload times render in UTC, values past what chrono can represent fall back to the number
+    #[test]
+    fn format_created_at_unit_test_1() {
+        assert_eq!(format_created_at(1_700_000_000_000), "2023-11-14 22:13:20 UTC");
+        assert_eq!(format_created_at(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_created_at(u64::MAX), u64::MAX.to_string());
+    }
+}
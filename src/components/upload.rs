@@ -0,0 +1,355 @@
+use super::messages::server_error_text;
+use crate::api::{ErrorResponse, UploadWasmResponse};
+use crate::app::UserContext;
+use crate::client::{submit_proof, watch_proof_status};
+use crate::components::{MessageContext, MessageSeverity, ProofList};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+#[cfg(feature = "ssr")]
+use std::sync::Arc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+async fn read_file(file: &web_sys::File) -> Result<Vec<u8>, String> {
+    let buffer = JsFuture::from(file.array_buffer())
+        .await
+        .map_err(|e| format!("could not read {}: {:?}", file.name(), e))?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+/// How far along an upload is, as far as the browser tells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UploadProgress {
+    /// The total size isn't known, or no progress was reported yet.
+    Indeterminate,
+    /// Percentage of the request body sent so far.
+    Percent(u8),
+}
+
+impl UploadProgress {
+    // From the fields of an upload `ProgressEvent`. `total` is only meaningful when `length_computable` is set.
+    fn from_event(loaded: f64, total: f64, length_computable: bool) -> Self {
+        if !length_computable || total <= 0.0 {
+            return Self::Indeterminate;
+        }
+        Self::Percent((loaded / total * 100.0).clamp(0.0, 100.0).floor() as u8)
+    }
+}
+
+// Posts `file` to `/upload_wasm` as a multipart form. Goes through `XMLHttpRequest` because, unlike `fetch`, it
+// reports how much of the body has been sent, which is handed to `on_progress` as it comes in.
+async fn send_upload(
+    file: &web_sys::File,
+    on_progress: impl Fn(UploadProgress) + 'static,
+) -> Result<UploadWasmResponse, String> {
+    let js_error = |e: JsValue| format!("{:?}", e);
+    let form = web_sys::FormData::new().map_err(js_error)?;
+    form.append_with_blob_and_filename("file", file, &file.name())
+        .map_err(js_error)?;
+    let xhr = web_sys::XmlHttpRequest::new().map_err(js_error)?;
+    xhr.open("POST", "/upload_wasm").map_err(js_error)?;
+
+    let progress = Closure::<dyn Fn(web_sys::ProgressEvent)>::new(move |ev: web_sys::ProgressEvent| {
+        on_progress(UploadProgress::from_event(
+            ev.loaded(),
+            ev.total(),
+            ev.length_computable(),
+        ))
+    });
+    // Should the events not arrive after all, the progress simply stays indeterminate.
+    let upload = xhr.upload().ok();
+    if let Some(upload) = &upload {
+        upload.set_onprogress(Some(progress.as_ref().unchecked_ref()));
+    }
+    let done = js_sys::Promise::new(&mut |resolve, reject| {
+        xhr.set_onload(Some(&resolve));
+        xhr.set_onerror(Some(&reject));
+        xhr.set_onabort(Some(&reject));
+    });
+    xhr.send_with_opt_form_data(Some(&form)).map_err(js_error)?;
+    let sent = JsFuture::from(done).await;
+    if let Some(upload) = &upload {
+        upload.set_onprogress(None);
+    }
+    sent.map_err(|_| "the connection was interrupted".to_string())?;
+
+    let status = xhr.status().map_err(js_error)?;
+    let body = xhr.response_text().map_err(js_error)?.unwrap_or_default();
+    if (200..300).contains(&status) {
+        serde_json::from_str(&body).map_err(|e| format!("unexpected response: {}", e))
+    } else {
+        Err(serde_json::from_str::<ErrorResponse>(&body)
+            .map(|error| error.error)
+            .unwrap_or_else(|_| format!("the server answered {}", status)))
+    }
+}
+
+// Copies `text` to the clipboard. Fails outside secure contexts, where there is no clipboard, and when the browser
+// denies access.
+pub(super) async fn copy_to_clipboard(text: &str) -> Result<(), String> {
+    let clipboard = window().navigator().clipboard();
+    if clipboard.is_undefined() {
+        return Err("clipboard not available".to_string());
+    }
+    JsFuture::from(clipboard.write_text(text))
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("{:?}", e))
+}
+
+// Selects everything inside `node`, so the user can copy it themselves.
+fn select_contents(node: &web_sys::Node) {
+    if let Some(selection) = window().get_selection().ok().flatten() {
+        let _ = selection.select_all_children(node);
+    }
+}
+
+/// Validates and stores a module. Same checks as the `/upload_wasm` route. The bytes travel Postcard-encoded, which
+/// leaves them as they are, so anything the route accepts fits in the body limit here too.
+#[server(name = UploadWasmModule, prefix = "/api", input = server_fn::codec::Postcard)]
+#[worker::send]
+pub async fn upload_wasm(bytes: Vec<u8>) -> Result<UploadWasmResponse, ServerFnError> {
+    use crate::handlers::RequestId;
+    use axum::Extension;
+    use leptos_axum::extract;
+    use worker::Env;
+
+    let Extension(env): Extension<Arc<Env>> = extract().await?;
+    let request_id: RequestId = extract().await?;
+    let response = crate::handlers::upload_wasm(&crate::handlers::wasm_bucket(&env)?, &request_id, bytes).await?;
+    Ok(response.0)
+}
+
+// Whether this browser can report how much of an upload has been sent.
+fn reports_upload_progress() -> bool {
+    web_sys::XmlHttpRequest::new().is_ok_and(|xhr| xhr.upload().is_ok())
+}
+
+// Uploads `file` through the `upload_wasm` server function, for browsers without upload progress.
+async fn upload_without_progress(file: &web_sys::File) -> Result<UploadWasmResponse, String> {
+    let bytes = read_file(file).await?;
+    upload_wasm(bytes).await.map_err(|e| server_error_text(&e))
+}
+
+#[component]
+pub fn UploadWasm() -> impl IntoView {
+    let message_ctx = expect_context::<MessageContext>();
+    let input = NodeRef::<leptos::html::Input>::new();
+    // `None` while no upload is running.
+    let (progress, set_progress) = signal(None::<UploadProgress>);
+    let (uploaded, set_uploaded) = signal(None::<(String, usize)>);
+    let hash_ref = NodeRef::<leptos::html::Code>::new();
+
+    let copy_hash = {
+        let message_ctx = message_ctx.clone();
+        move |hash: String| {
+            let message_ctx = message_ctx.clone();
+            spawn_local(async move {
+                match copy_to_clipboard(&hash).await {
+                    Ok(()) => message_ctx.add("Hash copied to clipboard", MessageSeverity::Success),
+                    Err(e) => {
+                        log::warn!("Failed to copy hash: {}", e);
+                        if let Some(code) = hash_ref.get() {
+                            select_contents(&code);
+                        }
+                        message_ctx.add(
+                            "Couldn't access the clipboard, the hash is selected for copying",
+                            MessageSeverity::Info,
+                        );
+                    }
+                }
+            });
+        }
+    };
+
+    let upload = move |_| {
+        let Some(file) = input
+            .get()
+            .and_then(|input| input.files())
+            .and_then(|files| files.get(0))
+        else {
+            message_ctx.add("Choose a .wasm file to upload", MessageSeverity::Warn);
+            return;
+        };
+        let message_ctx = message_ctx.clone();
+        set_progress.set(Some(UploadProgress::Indeterminate));
+        spawn_local(async move {
+            let result = if reports_upload_progress() {
+                send_upload(&file, move |progress| set_progress.set(Some(progress))).await
+            } else {
+                upload_without_progress(&file).await
+            };
+            match result {
+                Ok(response) => {
+                    let text = if response.already_existed {
+                        "Module was already uploaded"
+                    } else {
+                        "Module uploaded"
+                    };
+                    message_ctx.add(text, MessageSeverity::Success);
+                    set_uploaded.set(Some((response.hash, response.size)));
+                }
+                Err(e) => message_ctx.add(format!("Failed to upload module: {}", e), MessageSeverity::Error),
+            }
+            set_progress.set(None);
+        });
+    };
+
+    view! {
+        <div class="space-y-4">
+            <h2 class="text-2xl font-bold">"Upload a Test"</h2>
+            <div class="flex items-center space-x-2">
+                <input type="file" accept=".wasm,application/wasm" class="text-sm" node_ref=input />
+                <button
+                    class="px-4 py-2 bg-sky-700 text-white rounded hover:bg-sky-600 transition-colors disabled:opacity-50"
+                    disabled=move || progress.get().is_some()
+                    on:click=upload
+                >
+                    {move || if progress.get().is_some() { "Uploading..." } else { "Upload" }}
+                </button>
+            </div>
+            {move || progress.get().map(|progress| match progress {
+                UploadProgress::Percent(percent) => view! {
+                    <div
+                        class="w-full h-2 bg-gray-200 rounded dark:bg-gray-700"
+                        role="progressbar"
+                        aria-label="Upload progress"
+                        aria-valuemin="0"
+                        aria-valuemax="100"
+                        aria-valuenow=percent
+                    >
+                        <div class="h-2 bg-sky-700 rounded transition-all" style=format!("width: {}%", percent)></div>
+                    </div>
+                }.into_any(),
+                UploadProgress::Indeterminate => view! {
+                    <div class="flex items-center space-x-2 text-sm text-gray-500 dark:text-gray-400" role="status">
+                        <span class="inline-block w-4 h-4 border-2 border-sky-700 border-t-transparent rounded-full animate-spin"></span>
+                        <span>"Uploading..."</span>
+                    </div>
+                }.into_any(),
+            })}
+            {move || uploaded.get().map(|(hash, size)| {
+                let copy_hash = copy_hash.clone();
+                let copied = hash.clone();
+                view! {
+                    <div class="p-4 border rounded text-sm">
+                        <span class="text-gray-500">"SHA-256 "</span>
+                        <code class="break-all" node_ref=hash_ref>{hash.clone()}</code>
+                        <span class="ml-2 text-gray-500">{format!("{} bytes", size)}</span>
+                        <button
+                            class="ml-2 px-2 py-0.5 text-xs border rounded hover:bg-gray-100 dark:border-gray-600 dark:hover:bg-gray-700"
+                            on:click=move |_| copy_hash(copied.clone())
+                        >
+                            "Copy"
+                        </button>
+                    </div>
+                    <UploadProof wasm_hash=hash.clone() />
+                    <ProofList wasm_hash=hash />
+                }
+            })}
+        </div>
+    }
+}
+
+// The numbers a proof is made of, as `/upload_proof` names them, with the label shown for each.
+const PROOF_FIELDS: [(&str, &str); 6] = [
+    ("seed", "Seed"),
+    ("hash", "Hash"),
+    ("weight", "Weight"),
+    ("register", "Register"),
+    ("registers", "Registers"),
+    ("count", "Count"),
+];
+
+/// Submits a proof for the module `wasm_hash` and follows it until it is verified or rejected. Logged in users own
+/// the proofs they submit.
+#[component]
+fn UploadProof(wasm_hash: String) -> impl IntoView {
+    let message_ctx = expect_context::<MessageContext>();
+    let user_ctx = expect_context::<UserContext>();
+    let values = PROOF_FIELDS.map(|_| RwSignal::new(String::new()));
+    let (submitting, set_submitting) = signal(false);
+
+    let submit = move |_| {
+        let mut fields = vec![("wasm_hash", wasm_hash.clone())];
+        fields.extend(
+            PROOF_FIELDS
+                .iter()
+                .zip(values)
+                .map(|((name, _), value)| (*name, value.get_untracked())),
+        );
+        let message_ctx = message_ctx.clone();
+        let token = user_ctx.get_token();
+        let wasm_hash = wasm_hash.clone();
+        set_submitting.set(true);
+        spawn_local(async move {
+            match submit_proof(fields, token).await {
+                Ok(proof_hash) => watch_proof_status(&wasm_hash, &proof_hash, &message_ctx).await,
+                Err(e) => message_ctx.add(format!("Failed to submit proof: {}", e), MessageSeverity::Error),
+            }
+            set_submitting.set(false);
+        });
+    };
+
+    view! {
+        <div class="space-y-2">
+            <h3 class="text-lg font-semibold">"Submit a Proof"</h3>
+            <div class="grid grid-cols-3 gap-2">
+                {PROOF_FIELDS.iter().zip(values).map(|((_, label), value)| view! {
+                    <label class="text-sm space-y-1">
+                        <span class="block text-gray-500 dark:text-gray-400">{*label}</span>
+                        <input
+                            type="text"
+                            inputmode="numeric"
+                            class="w-full px-3 py-2 border rounded dark:bg-gray-700 dark:border-gray-600"
+                            prop:value=value
+                            on:input=move |ev| value.set(event_target_value(&ev))
+                        />
+                    </label>
+                }).collect_view()}
+            </div>
+            <button
+                class="px-4 py-2 bg-sky-700 text-white rounded hover:bg-sky-600 transition-colors disabled:opacity-50"
+                disabled=move || submitting.get()
+                on:click=submit
+            >
+                {move || if submitting.get() { "Checking..." } else { "Submit proof" }}
+            </button>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Progress is the share of the body sent, rounded down, and indeterminate without a known total
+    #[test]
+    fn upload_progress_unit_test_1() {
+        assert_eq!(
+            UploadProgress::from_event(0.0, 2048.0, true),
+            UploadProgress::Percent(0)
+        );
+        assert_eq!(
+            UploadProgress::from_event(1023.0, 2048.0, true),
+            UploadProgress::Percent(49)
+        );
+        assert_eq!(
+            UploadProgress::from_event(2048.0, 2048.0, true),
+            UploadProgress::Percent(100)
+        );
+        assert_eq!(
+            UploadProgress::from_event(4096.0, 2048.0, true),
+            UploadProgress::Percent(100)
+        );
+        assert_eq!(
+            UploadProgress::from_event(1024.0, 0.0, false),
+            UploadProgress::Indeterminate
+        );
+        assert_eq!(
+            UploadProgress::from_event(1024.0, 0.0, true),
+            UploadProgress::Indeterminate
+        );
+    }
+}
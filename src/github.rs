@@ -1,15 +1,52 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const GITHUB_CLIENT_ID: &str = "Ov23lixO0S9pamhwo1u7";
+
+/// OAuth scopes the app asks for. Only the organization listing needs `read:org`, the rest works without it.
+pub const GITHUB_SCOPES: [&str; 3] = ["read:project", "read:org", "read:user"];
+
+/// The scopes a login fails without.
+pub const REQUIRED_SCOPES: [&str; 2] = ["read:project", "read:user"];
+
+/// Whether `granted`, GitHub's comma separated scope list from the token exchange, covers `scope`. A `read:x` scope
+/// is also covered by the broader `x`, `write:x` or `admin:x`.
+pub fn has_scope(granted: &str, scope: &str) -> bool {
+    let resource = scope.trim_start_matches("read:");
+    granted.split(',').map(str::trim).any(|granted| {
+        granted == scope
+            || granted == resource
+            || granted.strip_prefix("write:") == Some(resource)
+            || granted.strip_prefix("admin:") == Some(resource)
+    })
+}
+
+/// The [`REQUIRED_SCOPES`] not covered by `granted`.
+pub fn missing_scopes(granted: &str) -> Vec<&'static str> {
+    REQUIRED_SCOPES
+        .into_iter()
+        .filter(|scope| !has_scope(granted, scope))
+        .collect()
+}
 
 // Wish I could use `octocrab` but it doesn't support WASM.
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Repository {
-    // pub name: String,
+    pub name: String,
     pub full_name: String,
     pub html_url: String,
     pub private: bool,
+    /// The one-line summary set on GitHub, `None` when the owner left it empty.
+    pub description: Option<String>,
+    /// Last push to any branch, `None` for a repository nothing was pushed to yet.
+    pub pushed_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub struct Organization {
     pub login: String,
     pub avatar_url: String,
@@ -18,10 +55,20 @@ pub struct Organization {
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
 pub struct User {
     pub login: String,
+    /// Unlike the login, never changes, so proofs are associated with it.
+    pub id: u64,
+    pub name: Option<String>,
     pub avatar_url: String,
     pub gravatar_id: String,
 }
 
+impl User {
+    /// The user's display name, or their login if they haven't set one.
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.login)
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct TokenResponse {
     pub access_token: String,
@@ -35,6 +82,211 @@ pub struct ErrorResponse {
     pub error_description: Option<String>,
 }
 
+impl ErrorResponse {
+    /// The error code followed by GitHub's description of it, which is the part a user can act on.
+    pub fn message(&self) -> String {
+        match self.error_description.as_deref().map(str::trim) {
+            Some(description) if !description.is_empty() => format!("{}: {}", self.error, description),
+            _ => self.error.clone(),
+        }
+    }
+}
+
+/// GitHub refused a request because the token ran out of API calls.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateLimited {
+    /// When the limit resets, in seconds since the Unix epoch.
+    pub reset: Option<u64>,
+}
+
+impl RateLimited {
+    /// A user-facing explanation of when access resumes, relative to `now` in seconds since the Unix epoch.
+    pub fn describe(&self, now: u64) -> String {
+        match self.reset {
+            Some(reset) if reset > now => {
+                let minutes = (reset - now).div_ceil(60);
+                format!(
+                    "GitHub rate limit exceeded, access resets in {} minute{}",
+                    minutes,
+                    if minutes == 1 { "" } else { "s" }
+                )
+            }
+            Some(_) => "GitHub rate limit exceeded, access resets momentarily".to_string(),
+            None => "GitHub rate limit exceeded, try again later".to_string(),
+        }
+    }
+}
+
+/// Detects GitHub's rate-limit responses: a `403` or `429` with `X-RateLimit-Remaining: 0`.
+pub fn check_rate_limit(status: StatusCode, headers: &HeaderMap) -> Option<RateLimited> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let limited = matches!(status, StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS)
+        && header("x-ratelimit-remaining") == Some("0");
+    limited.then(|| RateLimited {
+        reset: header("x-ratelimit-reset").and_then(|reset| reset.parse().ok()),
+    })
+}
+
+#[derive(Debug)]
+pub enum GithubError {
+    /// The request never got an answer.
+    Network(reqwest::Error),
+    /// GitHub didn't answer within [`GITHUB_TIMEOUT`].
+    Timeout,
+    /// GitHub rejected the token, it was revoked or has expired.
+    Unauthorized,
+    /// The token ran out of API calls, `reset` is when they come back in seconds since the Unix epoch.
+    RateLimited { reset: Option<u64> },
+    /// GitHub answered with a body that isn't what we asked for.
+    Decode(reqwest::Error),
+    /// Any other unsuccessful status.
+    Status(u16),
+}
+
+impl GithubError {
+    /// The error for an unsuccessful response, or `None` if the request succeeded.
+    pub fn from_status(status: StatusCode, headers: &HeaderMap) -> Option<Self> {
+        if status == StatusCode::UNAUTHORIZED {
+            return Some(GithubError::Unauthorized);
+        }
+        if let Some(RateLimited { reset }) = check_rate_limit(status, headers) {
+            return Some(GithubError::RateLimited { reset });
+        }
+        (!status.is_success()).then(|| GithubError::Status(status.as_u16()))
+    }
+
+    /// Passes successful responses through and turns the others into an error.
+    pub fn check(response: reqwest::Response) -> Result<reqwest::Response, Self> {
+        match Self::from_status(response.status(), response.headers()) {
+            Some(error) => Err(error),
+            None => Ok(response),
+        }
+    }
+}
+
+impl std::fmt::Display for GithubError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GithubError::Network(e) => write!(f, "request to GitHub failed: {}", e),
+            GithubError::Timeout => write!(f, "GitHub did not answer within {} seconds", GITHUB_TIMEOUT.as_secs()),
+            GithubError::Unauthorized => write!(f, "GitHub rejected the access token"),
+            GithubError::RateLimited { .. } => write!(f, "GitHub rate limit exceeded"),
+            GithubError::Decode(e) => write!(f, "unexpected response from GitHub: {}", e),
+            GithubError::Status(status) => write!(f, "GitHub answered with status {}", status),
+        }
+    }
+}
+
+impl std::error::Error for GithubError {}
+
+impl From<reqwest::Error> for GithubError {
+    fn from(value: reqwest::Error) -> Self {
+        match value.status() {
+            Some(status) => GithubError::Status(status.as_u16()),
+            None if value.is_decode() => GithubError::Decode(value),
+            None => GithubError::Network(value),
+        }
+    }
+}
+
+/// How long GitHub gets to answer a request.
+pub const GITHUB_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Sends `request`, giving up with [`GithubError::Timeout`] after [`GITHUB_TIMEOUT`]. reqwest has no timeouts on
+/// wasm, so the request races a timer instead.
+pub async fn send_with_timeout(request: reqwest::RequestBuilder) -> Result<reqwest::Response, GithubError> {
+    use futures_util::future::{select, Either};
+
+    let send = std::pin::pin!(request.send());
+    let timeout = std::pin::pin!(gloo_timers::future::sleep(GITHUB_TIMEOUT));
+    match select(send, timeout).await {
+        Either::Left((response, _)) => Ok(response?),
+        Either::Right(_) => Err(GithubError::Timeout),
+    }
+}
+
+/// How many times a GitHub request is retried after a transient server error.
+pub const GITHUB_RETRIES: u32 = 3;
+
+/// Delay before the first retry, doubling with every retry after it.
+pub const GITHUB_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Retries of the OAuth token exchange, fewer than [`GITHUB_RETRIES`] since the user is waiting on the login page.
+/// Together they add at most 1.5 seconds.
+pub const TOKEN_EXCHANGE_RETRIES: u32 = 2;
+
+/// Most requests a listing keeps in flight at once, to stay clear of GitHub's secondary rate limits.
+pub const GITHUB_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Runs `f` on every item with at most `limit` of the resulting futures pending at a time, returning the outputs in
+/// the order of `items`.
+pub async fn map_bounded<T, F, Fut>(items: impl IntoIterator<Item = T>, limit: usize, f: F) -> Vec<Fut::Output>
+where
+    F: FnMut(T) -> Fut,
+    Fut: std::future::Future,
+{
+    use futures_util::StreamExt;
+
+    futures_util::stream::iter(items.into_iter().map(f))
+        .buffered(limit.max(1))
+        .collect()
+        .await
+}
+
+/// Server errors that are usually gone when the request is repeated.
+pub fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// The delay before retry number `retry`, counting from zero.
+pub fn backoff_delay(base_delay: Duration, retry: u32) -> Duration {
+    base_delay.saturating_mul(2u32.saturating_pow(retry))
+}
+
+/// Sends the request built by `request`, building and sending it again up to `retries` times with exponential backoff
+/// while GitHub answers with a transient server error. Anything else, client errors included, is returned as is, and so
+/// is the last response once the retries are used up.
+pub async fn send_with_retry(
+    request: impl Fn() -> reqwest::RequestBuilder,
+    url: &str,
+    retries: u32,
+    base_delay: Duration,
+) -> Result<reqwest::Response, GithubError> {
+    let mut retry = 0;
+    loop {
+        let response = send_with_timeout(request()).await?;
+        if retry == retries || !is_transient(response.status()) {
+            return Ok(response);
+        }
+        log::warn!("GitHub answered {} for {}, retrying", response.status(), url);
+        gloo_timers::future::sleep(backoff_delay(base_delay, retry)).await;
+        retry += 1;
+    }
+}
+
+/// Sends an authenticated GET with [`send_with_retry`].
+pub async fn get_with_retry(
+    client: &reqwest::Client,
+    url: &str,
+    token: &str,
+    retries: u32,
+    base_delay: Duration,
+) -> Result<reqwest::Response, GithubError> {
+    send_with_retry(
+        || client.get(url).header("Authorization", format!("Bearer {}", token)),
+        url,
+        retries,
+        base_delay,
+    )
+    .await
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct UserAccessToken {
     pub access_token: String,
@@ -44,56 +296,143 @@ impl UserAccessToken {
     pub fn from_string(s: String) -> Self {
         Self { access_token: s }
     }
+}
 
-    pub async fn user(&self) -> Result<User, reqwest::Error> {
-        let client = reqwest::Client::new();
+/// Root of public GitHub's REST API.
+pub const GITHUB_API: &str = "https://api.github.com";
 
-        // First fetch user info to get login name
-        let user_response = client
-            .get("https://api.github.com/user")
-            .header("Authorization", format!("Bearer {}", self.access_token))
-            .header("User-Agent", "proof-of-tests")
-            .send()
-            .await?;
+/// Public GitHub's web host, which serves the OAuth endpoints.
+pub const GITHUB_WEB: &str = "https://github.com";
 
-        user_response.json::<User>().await
+/// REST API root, [`GITHUB_API`] unless `POT_GITHUB_API_URL` was set when building, e.g.
+/// `https://github.example.com/api/v3` for a GitHub Enterprise Server.
+pub fn github_api_url() -> &'static str {
+    option_env!("POT_GITHUB_API_URL").unwrap_or(GITHUB_API)
+}
+
+/// Web host the OAuth endpoints live on, [`GITHUB_WEB`] unless `POT_GITHUB_URL` was set when building.
+pub fn github_web_url() -> &'static str {
+    option_env!("POT_GITHUB_URL").unwrap_or(GITHUB_WEB)
+}
+
+/// Sent with every request to GitHub, which asks apps to identify themselves and their version.
+pub const GITHUB_USER_AGENT: &str = concat!("proof-of-tests/", env!("CARGO_PKG_VERSION"));
+
+/// REST API version the client is written against.
+pub const GITHUB_API_VERSION: &str = "2022-11-28";
+
+/// Headers every GitHub request carries, whatever the endpoint.
+pub fn github_default_headers() -> HeaderMap {
+    use reqwest::header::{HeaderValue, ACCEPT, USER_AGENT};
+
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static(GITHUB_USER_AGENT));
+    headers.insert(ACCEPT, HeaderValue::from_static("application/vnd.github+json"));
+    headers.insert("X-GitHub-Api-Version", HeaderValue::from_static(GITHUB_API_VERSION));
+    headers
+}
+
+/// A client sending [`github_default_headers`] with every request, leaving only `Authorization` to the caller.
+pub fn build_github_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .default_headers(github_default_headers())
+        .build()
+        .expect("GitHub client configuration is valid")
+}
+
+/// Authenticated access to the GitHub REST API. Every request carries the token and `User-Agent`, is retried on
+/// transient server errors and has `401`s and rate limits turned into a [`GithubError`].
+#[derive(Clone, Debug)]
+pub struct GithubClient {
+    client: reqwest::Client,
+    token: UserAccessToken,
+    api_url: String,
+}
+
+impl GithubClient {
+    /// A client talking to the API rooted at `api_url`, usually [`github_api_url`].
+    pub fn new(api_url: impl Into<String>, token: UserAccessToken) -> Self {
+        Self {
+            client: build_github_client(),
+            token,
+            api_url: api_url.into().trim_end_matches('/').to_string(),
+        }
     }
 
-    pub async fn organizations(&self, login: &str) -> Result<Vec<Organization>, reqwest::Error> {
-        let client = reqwest::Client::new();
-        let response = client
-            .get(format!("https://api.github.com/users/{}/orgs", login))
-            .header("Authorization", format!("Bearer {}", self.access_token))
-            .header("User-Agent", "proof-of-tests")
-            .send()
-            .await?;
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.api_url, path)
+    }
+
+    async fn get(&self, url: &str) -> Result<reqwest::Response, GithubError> {
+        let response = get_with_retry(
+            &self.client,
+            url,
+            &self.token.access_token,
+            GITHUB_RETRIES,
+            GITHUB_RETRY_DELAY,
+        )
+        .await?;
+
+        GithubError::check(response)
+    }
+
+    pub async fn current_user(&self) -> Result<User, GithubError> {
+        let response = self.get(&self.url("/user")).await?;
 
-        response.json::<Vec<Organization>>().await
+        Ok(response.json::<User>().await?)
     }
 
-    pub async fn org_repositories(&self, login: &str) -> Result<Vec<Repository>, reqwest::Error> {
-        let client = reqwest::Client::new();
-        let response = client
-            .get(format!("https://api.github.com/orgs/{}/repos", login))
-            .header("Authorization", format!("Bearer {}", self.access_token))
-            .header("User-Agent", "proof-of-tests")
-            .send()
-            .await?;
+    pub async fn list_user_orgs(&self, login: &str) -> Result<Vec<Organization>, GithubError> {
+        let response = self.get(&self.url(&format!("/users/{}/orgs", login))).await?;
 
-        response.json::<Vec<Repository>>().await
+        Ok(response.json::<Vec<Organization>>().await?)
     }
 
-    pub async fn user_repositories(&self) -> Result<Vec<Repository>, reqwest::Error> {
-        let client = reqwest::Client::new();
-        let response = client
-            .get("https://api.github.com/user/repos")
-            .header("Authorization", format!("Bearer {}", self.access_token))
-            .header("User-Agent", "proof-of-tests")
-            .send()
-            .await?;
+    pub async fn list_org_repos(&self, org: &str) -> Result<Vec<Repository>, GithubError> {
+        let response = self.get(&self.url(&format!("/orgs/{}/repos", org))).await?;
 
-        response.json::<Vec<Repository>>().await
+        Ok(response.json::<Vec<Repository>>().await?)
     }
+
+    /// Fetches all of the user's repositories, following GitHub's `Link` header from page to page. If a page fails,
+    /// the repositories loaded so far are returned together with the error.
+    pub async fn list_user_repos(&self) -> (Vec<Repository>, Option<GithubError>) {
+        let mut repositories = Vec::new();
+        let mut next = Some(self.url("/user/repos?per_page=100"));
+
+        while let Some(url) = next.take() {
+            let response = match self.get(&url).await {
+                Ok(response) => response,
+                Err(e) => return (repositories, Some(e)),
+            };
+
+            next = response
+                .headers()
+                .get(reqwest::header::LINK)
+                .and_then(|link| link.to_str().ok())
+                .and_then(next_page_url)
+                .map(str::to_string);
+
+            match response.json::<Vec<Repository>>().await {
+                Ok(page) => repositories.extend(page),
+                Err(e) => return (repositories, Some(e.into())),
+            }
+        }
+
+        (repositories, None)
+    }
+}
+
+/// Extracts the `rel="next"` URL from a GitHub `Link` header, e.g.
+/// `<https://api.github.com/user/repos?page=2>; rel="next", <https://api.github.com/user/repos?page=5>; rel="last"`.
+pub fn next_page_url(link: &str) -> Option<&str> {
+    link.split(',').find_map(|entry| {
+        let (url, params) = entry.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#)
+            .then(|| url.trim().trim_start_matches('<').trim_end_matches('>'))
+    })
 }
 
 #[cfg(test)]
@@ -122,12 +461,12 @@ mod tests {
 
         assert_eq!(repositories.len(), 2);
 
-        // assert_eq!(repositories[0].name, "repo1");
+        assert_eq!(repositories[0].name, "repo1");
         assert_eq!(repositories[0].full_name, "user/repo1");
         assert_eq!(repositories[0].html_url, "https://github.com/user/repo1");
         assert_eq!(repositories[0].private, false);
 
-        // assert_eq!(repositories[1].name, "repo2");
+        assert_eq!(repositories[1].name, "repo2");
         assert_eq!(repositories[1].full_name, "user/repo2");
         assert_eq!(repositories[1].html_url, "https://github.com/user/repo2");
         assert_eq!(repositories[1].private, true);
@@ -147,6 +486,11 @@ mod tests {
         let json = include_str!("../tests/org-repos.json");
         let repositories: Vec<Repository> = serde_json::from_str(json).unwrap();
         assert_eq!(repositories.len(), 6);
+        assert_eq!(
+            repositories[0].description.as_deref(),
+            Some("Computational Geometry library written in Rust")
+        );
+        assert!(repositories.iter().any(|repo| repo.description.is_none()));
     }
 
     // Test that User can be deserialized from a JSON string
@@ -163,6 +507,9 @@ mod tests {
 
         let user: User = serde_json::from_str(json).unwrap();
         assert_eq!(user.login, "octocat");
+        assert_eq!(user.id, 1);
+        assert_eq!(user.name, None);
+        assert_eq!(user.display_name(), "octocat");
         assert_eq!(user.avatar_url, "https://github.com/images/error/octocat_happy.gif");
         assert_eq!(user.gravatar_id, "");
     }
@@ -173,5 +520,270 @@ mod tests {
         let json = include_str!("../tests/user.json");
         let user: User = serde_json::from_str(json).unwrap();
         assert!(user.login.len() > 0);
+        assert_eq!(user.display_name(), "David Himmelstrup");
+    }
+
+    // A `null` name falls back to the login
+    #[test]
+    fn user_json_unit_test_3() {
+        let json = r#"{
+            "login": "octocat",
+            "id": 1,
+            "name": null,
+            "avatar_url": "https://github.com/images/error/octocat_happy.gif",
+            "gravatar_id": ""
+        }"#;
+
+        let user: User = serde_json::from_str(json).unwrap();
+        assert_eq!(user.name, None);
+        assert_eq!(user.display_name(), "octocat");
+    }
+
+    // The `next` link is found regardless of its position in the header
+    #[test]
+    fn link_header_unit_test_1() {
+        let link = r#"<https://api.github.com/user/repos?per_page=100&page=2>; rel="next", <https://api.github.com/user/repos?per_page=100&page=4>; rel="last""#;
+        assert_eq!(
+            next_page_url(link),
+            Some("https://api.github.com/user/repos?per_page=100&page=2")
+        );
+
+        let link = r#"<https://api.github.com/user/repos?page=1>; rel="prev", <https://api.github.com/user/repos?page=3>; rel="next""#;
+        assert_eq!(next_page_url(link), Some("https://api.github.com/user/repos?page=3"));
+    }
+
+    // The last page has no `next` link
+    #[test]
+    fn link_header_unit_test_2() {
+        let link = r#"<https://api.github.com/user/repos?page=1>; rel="first", <https://api.github.com/user/repos?page=3>; rel="prev""#;
+        assert_eq!(next_page_url(link), None);
+        assert_eq!(next_page_url(""), None);
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+            .collect()
+    }
+
+    // A 403 with no remaining calls is a rate limit, and carries the reset time
+    #[test]
+    fn rate_limit_unit_test_1() {
+        let recorded = headers(&[
+            ("x-ratelimit-limit", "5000"),
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1700000600"),
+            ("x-ratelimit-used", "5000"),
+        ]);
+        assert_eq!(
+            check_rate_limit(StatusCode::FORBIDDEN, &recorded),
+            Some(RateLimited {
+                reset: Some(1700000600)
+            })
+        );
+        assert!(check_rate_limit(StatusCode::TOO_MANY_REQUESTS, &recorded).is_some());
+    }
+
+    // A 403 with calls left, or a success with none left, isn't a rate limit
+    #[test]
+    fn rate_limit_unit_test_2() {
+        let remaining = headers(&[("x-ratelimit-remaining", "4999"), ("x-ratelimit-reset", "1700000600")]);
+        assert_eq!(check_rate_limit(StatusCode::FORBIDDEN, &remaining), None);
+
+        let exhausted = headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "1700000600")]);
+        assert_eq!(check_rate_limit(StatusCode::OK, &exhausted), None);
+        assert_eq!(check_rate_limit(StatusCode::FORBIDDEN, &HeaderMap::new()), None);
+    }
+
+    // The message says when access resets
+    #[test]
+    fn rate_limit_unit_test_3() {
+        let limit = RateLimited {
+            reset: Some(1700000600),
+        };
+        assert_eq!(
+            limit.describe(1700000000),
+            "GitHub rate limit exceeded, access resets in 10 minutes"
+        );
+        assert_eq!(
+            limit.describe(1700000599),
+            "GitHub rate limit exceeded, access resets in 1 minute"
+        );
+        assert_eq!(
+            limit.describe(1700000600),
+            "GitHub rate limit exceeded, access resets momentarily"
+        );
+        assert_eq!(
+            RateLimited { reset: None }.describe(1700000000),
+            "GitHub rate limit exceeded, try again later"
+        );
+    }
+
+    // Only 500, 502, 503 and 504 are retried
+    #[test]
+    fn retry_unit_test_1() {
+        for status in [500, 502, 503, 504] {
+            assert!(is_transient(StatusCode::from_u16(status).unwrap()));
+        }
+        for status in [200, 304, 401, 403, 404, 422, 501] {
+            assert!(!is_transient(StatusCode::from_u16(status).unwrap()));
+        }
+    }
+
+    // The delay doubles with every retry
+    #[test]
+    fn retry_unit_test_2() {
+        let base = Duration::from_millis(10);
+        assert_eq!(backoff_delay(base, 0), Duration::from_millis(10));
+        assert_eq!(backoff_delay(base, 1), Duration::from_millis(20));
+        assert_eq!(backoff_delay(base, 2), Duration::from_millis(40));
+        assert_eq!(
+            backoff_delay(GITHUB_RETRY_DELAY, GITHUB_RETRIES - 1),
+            Duration::from_secs(2)
+        );
+    }
+
+    // Retrying the token exchange keeps the added wait under two seconds
+    #[test]
+    fn retry_unit_test_3() {
+        let added = (0..TOKEN_EXCHANGE_RETRIES)
+            .map(|retry| backoff_delay(GITHUB_RETRY_DELAY, retry))
+            .sum::<Duration>();
+        assert_eq!(added, Duration::from_millis(1500));
+    }
+
+    // Endpoints are resolved against the API root, with or without a trailing slash
+    #[test]
+    fn github_client_unit_test_1() {
+        let token = UserAccessToken::from_string("token".into());
+        assert_eq!(
+            GithubClient::new(GITHUB_API, token.clone()).url("/user"),
+            "https://api.github.com/user"
+        );
+        assert_eq!(
+            GithubClient::new("http://localhost:8080/", token).url("/orgs/rust-lang/repos"),
+            "http://localhost:8080/orgs/rust-lang/repos"
+        );
+    }
+
+    // A timeout says how long GitHub was given
+    #[test]
+    fn github_error_unit_test_4() {
+        assert_eq!(
+            GithubError::Timeout.to_string(),
+            "GitHub did not answer within 15 seconds"
+        );
+    }
+
+    // Every request identifies the app and pins the API version
+    #[test]
+    fn github_default_headers_unit_test_1() {
+        let headers = github_default_headers();
+        assert!(headers["user-agent"].to_str().unwrap().starts_with("proof-of-tests/"));
+        assert_eq!(headers["accept"], "application/vnd.github+json");
+        assert_eq!(headers["x-github-api-version"], "2022-11-28");
+    }
+
+    // A 401 means the token is no longer valid
+    #[test]
+    fn github_error_unit_test_1() {
+        let recorded = headers(&[
+            ("content-type", "application/json; charset=utf-8"),
+            ("x-github-media-type", "github.v3; format=json"),
+        ]);
+        assert!(matches!(
+            GithubError::from_status(StatusCode::UNAUTHORIZED, &recorded),
+            Some(GithubError::Unauthorized)
+        ));
+    }
+
+    // A 403 with exhausted rate-limit headers is a rate limit, other 403s are plain status errors
+    #[test]
+    fn github_error_unit_test_2() {
+        let recorded = headers(&[
+            ("x-ratelimit-limit", "5000"),
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1700000600"),
+            ("x-ratelimit-used", "5000"),
+        ]);
+        assert!(matches!(
+            GithubError::from_status(StatusCode::FORBIDDEN, &recorded),
+            Some(GithubError::RateLimited {
+                reset: Some(1700000600)
+            })
+        ));
+        assert!(matches!(
+            GithubError::from_status(StatusCode::FORBIDDEN, &HeaderMap::new()),
+            Some(GithubError::Status(403))
+        ));
+    }
+
+    // Server errors keep their status code, and successful responses are no error at all
+    #[test]
+    fn github_error_unit_test_3() {
+        let recorded = headers(&[("content-type", "application/json; charset=utf-8")]);
+        assert!(matches!(
+            GithubError::from_status(StatusCode::INTERNAL_SERVER_ERROR, &recorded),
+            Some(GithubError::Status(500))
+        ));
+        assert!(GithubError::from_status(StatusCode::OK, &recorded).is_none());
+        assert_eq!(GithubError::Status(500).to_string(), "GitHub answered with status 500");
+    }
+
+    // All requested scopes, in any order, leave nothing missing
+    #[test]
+    fn scope_unit_test_1() {
+        assert!(missing_scopes("read:org,read:project,read:user").is_empty());
+        assert!(missing_scopes("read:user, read:project, read:org").is_empty());
+    }
+
+    // Broader scopes cover the read-only ones, anything else is reported in request order
+    #[test]
+    fn scope_unit_test_2() {
+        assert!(missing_scopes("admin:org,project,user").is_empty());
+        assert_eq!(missing_scopes("read:user"), ["read:project"]);
+        assert_eq!(missing_scopes(""), REQUIRED_SCOPES);
+        assert_eq!(missing_scopes("read:organization,repo"), REQUIRED_SCOPES);
+    }
+
+    // Organization access is optional, a login without it still has everything it needs
+    #[test]
+    fn scope_unit_test_3() {
+        assert!(missing_scopes("read:project,read:user").is_empty());
+        assert!(!has_scope("read:project,read:user", "read:org"));
+        assert!(has_scope("read:project, write:org", "read:org"));
+        assert!(!has_scope("", "read:org"));
+    }
+
+    // Bounded mapping keeps the input order, even with a limit of zero
+    #[test]
+    fn map_bounded_unit_test_1() {
+        use futures_util::FutureExt;
+
+        let doubled = map_bounded(1..=10, GITHUB_MAX_CONCURRENT_REQUESTS, |n| async move { n * 2 })
+            .now_or_never()
+            .unwrap();
+        assert_eq!(doubled, (1..=10).map(|n| n * 2).collect::<Vec<_>>());
+        assert_eq!(
+            map_bounded([1, 2], 0, std::future::ready).now_or_never().unwrap(),
+            [1, 2]
+        );
+    }
+
+    // The description is appended to the error code when GitHub sends one
+    #[test]
+    fn error_response_unit_test_1() {
+        let error: ErrorResponse = serde_json::from_str(
+            r#"{"error":"bad_verification_code","error_description":"The code passed is incorrect or expired.","error_uri":"https://docs.github.com"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            error.message(),
+            "bad_verification_code: The code passed is incorrect or expired."
+        );
+
+        let error: ErrorResponse = serde_json::from_str(r#"{"error":"incorrect_client_credentials"}"#).unwrap();
+        assert_eq!(error.message(), "incorrect_client_credentials");
     }
 }
@@ -1,15 +1,36 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
 use anyhow::Context as _;
-use axum::response::IntoResponse;
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
 
-use axum::extract::{Multipart, Query};
+use axum::async_trait;
+use axum::extract::multipart::Field;
+use axum::extract::{FromRequestParts, Multipart, Path, Query};
+use axum::routing::{get, post, put};
 
-use axum::Extension;
-use http::StatusCode;
+use axum::{Extension, Json, Router};
+use futures_util::future::try_join_all;
+use http::request::Parts;
+use http::{header, HeaderMap, StatusCode};
+use leptos::prelude::ServerFnError;
 use serde::Deserialize;
-use wasmi::*;
-use worker::{query, Env};
+use worker::Env;
+
+use crate::api::{
+    ErrorResponse, HealthResponse, ProofListResponse, ProofState, ProofStatusResponse, UploadWasmResponse,
+    ValidateResponse, VersionResponse,
+};
+use crate::github::{github_api_url, github_web_url, GithubClient, GithubError, UserAccessToken};
+use crate::hash::{is_sha256_hex, sha256_hex, IncrementalSha256};
+use crate::metrics;
+use crate::proof::{Proof, ProofOwner};
+use crate::storage::Storage;
 
 // Idempotent WASM uploader
 // Proof uploader
@@ -17,27 +38,61 @@ use worker::{query, Env};
 //  - Check if proof is valid
 //  - Store proof
 
-// Proof associations:
-//  - Anonymous
-//  - Github ID
+/// Error returned by the handlers, answered as `{"error": "..."}` with its status code.
+pub struct AppError {
+    status: StatusCode,
+    message: String,
+    field: Option<String>,
+}
+
+impl AppError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            field: None,
+        }
+    }
+
+    /// A `400` for a required multipart field the request left out, `{"error":"missing_field","field":name}`.
+    pub fn missing_field(name: &str) -> Self {
+        Self {
+            field: Some(name.to_string()),
+            ..Self::new(StatusCode::BAD_REQUEST, "missing_field")
+        }
+    }
 
-// Proof table:
-//  - wasm hash
-//  - owner
-//  - created at
-//  - seed
-//  - hash
-//  - weight
-//  - register
-//  - registers
-//  - count
+    /// A `503` for a deployment without the R2 bucket binding `binding`.
+    pub fn missing_bucket(binding: &str) -> Self {
+        Self::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("R2 bucket binding `{}` is not configured", binding),
+        )
+    }
 
-pub struct AppError(axum::response::Response);
+    /// A `400` for a field whose value is unusable, with the field named next to the message.
+    pub fn invalid_field(name: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: Some(name.to_string()),
+            ..Self::new(StatusCode::BAD_REQUEST, message)
+        }
+    }
+}
 
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
-        self.0
-        // (StatusCode::INTERNAL_SERVER_ERROR, self.0.to_string()).into_response()
+        let body = ErrorResponse {
+            error: self.message,
+            field: self.field,
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+// Lets server functions reuse the route logic with `?`
+impl From<AppError> for ServerFnError {
+    fn from(value: AppError) -> Self {
+        ServerFnError::new(value.message)
     }
 }
 
@@ -46,32 +101,232 @@ where
     E: Into<anyhow::Error>,
 {
     fn from(value: E) -> Self {
-        Self((StatusCode::INTERNAL_SERVER_ERROR, anyhow::anyhow!(value).to_string()).into_response())
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, anyhow::anyhow!(value).to_string())
     }
 }
 
-// #[axum::debug_handler]
-pub async fn validate_handler(mut payload: Multipart) -> impl IntoResponse {
-    while let Some(field) = payload.next_field().await.unwrap() {
-        if field.name() == Some("file") {
-            let data = field.bytes().await.unwrap();
-            log::info!("File length: {}", data.len());
-
-            let engine = Engine::default();
-            let module = Module::new(&engine, &data).unwrap();
-            let mut store = Store::new(&engine, ());
-            let linker = Linker::new(&engine);
-            let instance = linker
-                .instantiate(&mut store, &module)
-                .unwrap()
-                .start(&mut store)
-                .unwrap();
-            let test = instance.get_typed_func::<u64, u64>(&mut store, "test").unwrap();
-            let result = test.call(&mut store, 42).unwrap();
-            log::info!("Test result: {}", result);
+/// Tags the log lines of a single request: Cloudflare's `cf-ray` id when present, otherwise a short generated one.
+pub struct RequestId(String);
+
+static NEXT_REQUEST: AtomicU32 = AtomicU32::new(0);
+
+impl RequestId {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        match headers.get("cf-ray").and_then(|ray| ray.to_str().ok()) {
+            Some(ray) if !ray.is_empty() => Self(ray.to_string()),
+            _ => Self::generate(worker::Date::now().as_millis()),
         }
     }
-    "Hello world"
+
+    // A short id for a request Cloudflare didn't tag. Mixes the clock, `now` in milliseconds, with a per-isolate
+    // counter so concurrent requests don't collide.
+    fn generate(now: u64) -> Self {
+        Self(format!(
+            "{:08x}",
+            (now as u32) ^ NEXT_REQUEST.fetch_add(1, Ordering::Relaxed).wrapping_mul(0x9e37_79b9)
+        ))
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RequestId {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+/// Largest WASM module accepted by the upload and validation routes.
+pub const MAX_WASM_SIZE: usize = 8 * 1024 * 1024;
+
+/// Largest value accepted for any other multipart field.
+pub const MAX_FIELD_SIZE: usize = 1024;
+
+/// Limit for a whole request body, leaving room for the multipart framing and small fields around a module.
+pub const MAX_BODY_SIZE: usize = MAX_WASM_SIZE + 64 * 1024;
+
+/// Proofs listed per page unless `?limit=` asks for something else.
+const DEFAULT_PROOF_LIST_LIMIT: u32 = 100;
+
+/// R2 returns at most this many keys per list call.
+const MAX_PROOF_LIST_LIMIT: u32 = 1000;
+
+/// Keys under this prefix in the `proofs` bucket point at the proofs of one owner, see [`owner_index_key`].
+const OWNER_INDEX_PREFIX: &str = "by-owner";
+
+// Reads a multipart field chunk by chunk, giving up with a `413` as soon as it grows past `limit` bytes. Each chunk is
+// also handed to `on_chunk` as it arrives.
+async fn read_field_with(
+    mut field: Field<'_>,
+    limit: usize,
+    mut on_chunk: impl FnMut(&[u8]),
+) -> Result<Vec<u8>, AppError> {
+    let name = field.name().unwrap_or_default().to_string();
+    let mut data = Vec::new();
+    while let Some(chunk) = field.chunk().await? {
+        if data.len() + chunk.len() > limit {
+            return Err(AppError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Field `{}` is larger than {} bytes", name, limit),
+            ));
+        }
+        on_chunk(&chunk);
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+async fn read_field(field: Field<'_>, limit: usize) -> Result<Vec<u8>, AppError> {
+    read_field_with(field, limit, |_| {}).await
+}
+
+async fn read_text_field(field: Field<'_>) -> Result<String, AppError> {
+    let name = field.name().unwrap_or_default().to_string();
+    String::from_utf8(read_field(field, MAX_FIELD_SIZE).await?)
+        .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, format!("Field `{}` is not valid UTF-8", name)))
+}
+
+/// Routes that never look at the worker `Env`.
+pub fn stateless_routes<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    Router::new()
+        .route("/version", get(version_handler))
+        .route("/metrics", get(metrics_handler))
+}
+
+/// Routes reading the worker `Env` from an `Extension`, for the R2 buckets or, in `/health`, to report on them.
+/// `/validate` belongs here too, it caches results next to the module in the `wasm` bucket.
+pub fn storage_routes<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    Router::new()
+        .route("/health", get(health_handler))
+        .route("/validate", post(validate_handler))
+        .route("/upload_wasm", post(upload_wasm_handler))
+        .route("/wasm/:hash", get(download_wasm_handler))
+        .route("/upload_proof", put(upload_proof_handler))
+        .route("/proofs/:wasm_hash", get(list_proofs_handler))
+        .route("/proofs/:wasm_hash/:proof_hash/status", get(proof_status_handler))
+}
+
+// Uptime check, also reporting whether the R2 binding for modules is configured. Without a worker `Env` there is no
+// binding to report.
+#[axum::debug_handler]
+#[worker::send]
+pub async fn health_handler(env: Option<Extension<Arc<Env>>>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok".to_string(),
+        wasm_bucket: env.is_some_and(|Extension(env)| wasm_bucket(&env).is_ok()),
+    })
+}
+
+// Which build is deployed, from the metadata `build.rs` records
+pub async fn version_handler() -> Json<VersionResponse> {
+    Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_sha: option_env!("POT_GIT_SHA").unwrap_or("unknown").to_string(),
+        built_at: option_env!("POT_BUILT_AT")
+            .and_then(|built_at| built_at.parse().ok())
+            .unwrap_or_default(),
+    })
+}
+
+// Counters of the instance that happens to answer, see `metrics` for what that means
+pub async fn metrics_handler() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, metrics::CONTENT_TYPE)], metrics::render())
+}
+
+/// Part of every validation cache key. Bump it whenever `crate::wasm` changes what it accepts, or `/validate` what it
+/// reports, so results of the old rules stop being served.
+const VALIDATOR_VERSION: u32 = 2;
+
+// Where the result of calling `func(arg)` on the module with this hash is cached, next to the module in the `wasm`
+// bucket.
+fn validation_cache_key(hash: &str, func: &str, arg: u64) -> String {
+    format!("{}.{}.{}.v{}.result.json", hash, func, arg, VALIDATOR_VERSION)
+}
+
+async fn cached_validation(wasm: &impl Storage, key: &str) -> anyhow::Result<Option<ValidateResponse>> {
+    match wasm.get(key).await? {
+        Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+        None => Ok(None),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ValidateParams {
+    #[serde(default)]
+    force: bool,
+}
+
+// Runs an uploaded module without storing it. The `func` and `arg` fields pick the export to call and its argument,
+// defaulting to `test(42)`. Results are cached per module, `?force=true` runs the module again regardless. The module
+// may also be sent as WAT text, which is compiled first and then handled like the binary it compiles to.
+#[axum::debug_handler]
+#[worker::send]
+pub async fn validate_handler(
+    Extension(env): Extension<Arc<Env>>,
+    request_id: RequestId,
+    Query(params): Query<ValidateParams>,
+    mut payload: Multipart,
+) -> Result<impl IntoResponse, AppError> {
+    let mut data = None;
+    let mut func = "test".to_string();
+    let mut arg = 42;
+    while let Some(field) = payload.next_field().await? {
+        match field.name() {
+            Some("file") => data = Some(read_field(field, MAX_WASM_SIZE).await?),
+            Some("func") => func = read_text_field(field).await?,
+            Some("arg") => {
+                arg =
+                    read_text_field(field).await?.trim().parse().map_err(|_| {
+                        AppError::new(StatusCode::BAD_REQUEST, "`arg` must be an unsigned 64-bit integer")
+                    })?
+            }
+            _ => {}
+        }
+    }
+    let data = data.ok_or_else(|| AppError::missing_field("file"))?;
+    let data = crate::wasm::wat_to_wasm(&data)
+        .map_err(|e| AppError::invalid_field("file", format!("{:#}", e)))?
+        .into_owned();
+    let wasm = wasm_bucket(&env)?;
+    let cache_key = validation_cache_key(&sha256_hex(&data), &func, arg);
+    if !params.force {
+        // A broken cache only costs a recompile.
+        match cached_validation(&wasm, &cache_key).await {
+            Ok(Some(cached)) => {
+                log::info!("[{}] Using cached result {}", request_id, cache_key);
+                metrics::VALIDATIONS_PASSED.inc();
+                return Ok(Json(cached));
+            }
+            Ok(None) => {}
+            Err(e) => log::error!("[{}] Failed to read cached result {}: {:#}", request_id, cache_key, e),
+        }
+    }
+
+    log::info!("[{}] Validating {}({}) on {} bytes", request_id, func, arg, data.len());
+    let (result, info) = crate::wasm::run_test_with_info(&data, &func, arg).map_err(|e| {
+        log::info!("[{}] Validation failed: {:#}", request_id, e);
+        metrics::VALIDATIONS_FAILED.inc();
+        AppError::new(StatusCode::BAD_REQUEST, format!("{:#}", e))
+    })?;
+    metrics::VALIDATIONS_PASSED.inc();
+    let response = ValidateResponse {
+        func,
+        arg,
+        result,
+        size: info.size,
+        exported_functions: info.exported_functions,
+    };
+    if let Err(e) = Storage::put(&wasm, &cache_key, serde_json::to_vec(&response)?).await {
+        log::error!("[{}] Failed to cache result {}: {:#}", request_id, cache_key, e);
+    }
+    Ok(Json(response))
 }
 
 // Idempotent WASM uploader
@@ -79,69 +334,1305 @@ pub async fn validate_handler(mut payload: Multipart) -> impl IntoResponse {
 #[axum::debug_handler]
 #[worker::send]
 pub async fn upload_wasm_handler(
-    Extension(env): Extension<Arc<Env>>,
+    store: WasmStore,
+    request_id: RequestId,
     mut payload: Multipart,
 ) -> Result<impl IntoResponse, AppError> {
+    let (data, hash) = read_wasm_upload(&mut payload).await?;
+    match store {
+        WasmStore::Env(env) => store_wasm(&wasm_bucket(&env)?, &request_id, data, hash).await,
+        #[cfg(test)]
+        WasmStore::Memory(storage) => store_wasm(&*storage, &request_id, data, hash).await,
+    }
+}
+
+/// Where `/upload_wasm` keeps modules: the `wasm` bucket of the worker `Env`, or in tests a [`MemoryStorage`] handed
+/// to the route in an `Extension`. R2 buckets can't cross threads, so the bucket is only looked up in the handler.
+///
+/// [`MemoryStorage`]: crate::storage::MemoryStorage
+pub enum WasmStore {
+    Env(Arc<Env>),
+    #[cfg(test)]
+    Memory(Arc<crate::storage::MemoryStorage>),
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for WasmStore {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        #[cfg(test)]
+        if let Some(storage) = parts.extensions.get::<Arc<crate::storage::MemoryStorage>>() {
+            return Ok(Self::Memory(storage.clone()));
+        }
+        parts
+            .extensions
+            .get::<Arc<Env>>()
+            .map(|env| Self::Env(env.clone()))
+            .ok_or_else(|| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Worker environment is missing"))
+    }
+}
+
+// Reads the `file` field of an upload, hashing it while the chunks come in rather than in a second pass. The module
+// still ends up in memory, validating it and keying it by its hash both need all of it.
+async fn read_wasm_upload(payload: &mut Multipart) -> Result<(Vec<u8>, String), AppError> {
     while let Some(field) = payload.next_field().await? {
         if field.name() == Some("file") {
-            let data = field.bytes().await?;
-            log::info!("File length: {}", data.len());
-            // Calculate the hash of the data
-            let hash = {
-                use sha2::{Digest, Sha256};
-                let mut hasher = Sha256::new();
-                hasher.update(&data);
-                format!("{:x}", hasher.finalize())
-            };
-            let vec = data.to_vec();
-            env.bucket("wasm")?.put(&hash, vec).execute().await?;
-            return Ok(hash);
+            let mut hasher = IncrementalSha256::default();
+            let data = read_field_with(field, MAX_WASM_SIZE, |chunk| hasher.update(chunk)).await?;
+            return Ok((data, hasher.finish()));
         }
     }
-    Err(AppError((StatusCode::BAD_REQUEST, "No file found").into_response()))
+    Err(AppError::missing_field("file"))
+}
+
+pub async fn upload_wasm(
+    wasm: &impl Storage,
+    request_id: &RequestId,
+    data: Vec<u8>,
+) -> Result<Json<UploadWasmResponse>, AppError> {
+    let hash = sha256_hex(&data);
+    store_wasm(wasm, request_id, data, hash).await
+}
+
+// Validates a module and stores it under `hash`, which must be the SHA-256 of `data`
+async fn store_wasm(
+    wasm: &impl Storage,
+    request_id: &RequestId,
+    data: Vec<u8>,
+    hash: String,
+) -> Result<Json<UploadWasmResponse>, AppError> {
+    log::info!("[{}] Uploading {} bytes", request_id, data.len());
+    if !crate::wasm::has_wasm_magic(&data) {
+        metrics::UPLOADS_REJECTED.inc();
+        return Err(AppError::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "File is not a WASM module",
+        ));
+    }
+    crate::wasm::validate_test_module(&data, "test").map_err(|e| {
+        log::info!("[{}] Rejected module: {:#}", request_id, e);
+        metrics::UPLOADS_REJECTED.inc();
+        AppError::new(StatusCode::BAD_REQUEST, format!("{:#}", e))
+    })?;
+    let size = data.len();
+    let already_existed = wasm.head(&hash).await?;
+    if !already_existed {
+        wasm.put(&hash, data).await?;
+        metrics::BYTES_STORED.add(size as u64);
+    }
+    metrics::UPLOADS_ACCEPTED.inc();
+    log::info!(
+        "[{}] Stored module {} (already existed: {})",
+        request_id,
+        hash,
+        already_existed
+    );
+    Ok(Json(UploadWasmResponse {
+        hash,
+        size,
+        already_existed,
+    }))
+}
+
+/// Modules never change once stored under their hash, so they may be cached for as long as caches allow.
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+// Whether an `If-None-Match` header value lists `etag`, or is `*`. Weak validators match too, as the spec asks for
+// `If-None-Match`.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|candidate| candidate.trim())
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+// Serves a stored module by its hash, answering `304` when the client already has it
+#[axum::debug_handler]
+#[worker::send]
+pub async fn download_wasm_handler(
+    Extension(env): Extension<Arc<Env>>,
+    Path(hash): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let if_none_match = headers.get(header::IF_NONE_MATCH).and_then(|value| value.to_str().ok());
+    download_wasm(&wasm_bucket(&env)?, hash, if_none_match).await
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ProofParams {
-    wasm: String,
-    seed: u64,
-    hash: u64,
+async fn download_wasm(wasm: &impl Storage, hash: String, if_none_match: Option<&str>) -> Result<Response, AppError> {
+    let not_found = || AppError::new(StatusCode::NOT_FOUND, "WASM not found");
+    if !is_sha256_hex(&hash) {
+        return Err(not_found());
+    }
+    let etag = format!("\"{}\"", hash);
+    if if_none_match.is_some_and(|value| etag_matches(value, &etag)) {
+        if !wasm.head(&hash).await? {
+            return Err(not_found());
+        }
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, etag),
+                (header::CACHE_CONTROL, IMMUTABLE_CACHE_CONTROL.to_string()),
+            ],
+        )
+            .into_response());
+    }
+    let data = wasm.get(&hash).await?.ok_or_else(not_found)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/wasm".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.wasm\"", hash),
+            ),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, IMMUTABLE_CACHE_CONTROL.to_string()),
+        ],
+        data,
+    )
+        .into_response())
 }
 
+// Proof uploader
+// Checks the proof against its WASM module and stores it with the verdict in R2 under `{wasm_hash}/{proof_hash}`, where
+// the proof hash is the SHA-256 of the proof's content. Rejected proofs are kept too, so their status can be looked up,
+// but the upload still fails with a `400`.
 #[axum::debug_handler]
 #[worker::send]
 pub async fn upload_proof_handler(
     Extension(env): Extension<Arc<Env>>,
-    Query(params): Query<ProofParams>,
+    request_id: RequestId,
+    headers: HeaderMap,
+    mut payload: Multipart,
 ) -> Result<impl IntoResponse, AppError> {
-    let bucket = env.bucket("wasm").unwrap();
-    let wasm_object = bucket
-        .get(&params.wasm)
-        .execute()
+    let owner = proof_owner(github_api_url(), &headers).await?;
+    let mut fields = HashMap::new();
+    while let Some(field) = payload.next_field().await? {
+        if let Some(name) = field.name().map(str::to_string) {
+            fields.insert(name, read_text_field(field).await?);
+        }
+    }
+    let proof = proof_from_fields(&fields, owner, worker::Date::now().as_millis())?;
+    upload_proof(&wasm_bucket(&env)?, &proofs_bucket(&env)?, &request_id, proof).await
+}
+
+fn text_field<'a>(fields: &'a HashMap<String, String>, name: &str) -> Result<&'a str, AppError> {
+    fields
+        .get(name)
+        .map(|value| value.trim())
+        .ok_or_else(|| AppError::missing_field(name))
+}
+
+fn number_field<T: std::str::FromStr>(fields: &HashMap<String, String>, name: &str) -> Result<T, AppError> {
+    let value = text_field(fields, name)?;
+    value.parse().map_err(|_| {
+        AppError::invalid_field(
+            name,
+            format!("`{}` must be a {}, got `{}`", name, std::any::type_name::<T>(), value),
+        )
+    })
+}
+
+// The proof described by the fields of an upload. Numbers must fit their type and the register must be one of the
+// `registers`, anything else is a `400` naming the field.
+fn proof_from_fields(fields: &HashMap<String, String>, owner: ProofOwner, created_at: u64) -> Result<Proof, AppError> {
+    let proof = Proof {
+        wasm_hash: text_field(fields, "wasm_hash")?.to_string(),
+        owner,
+        created_at,
+        seed: number_field(fields, "seed")?,
+        hash: number_field(fields, "hash")?,
+        weight: number_field(fields, "weight")?,
+        register: number_field(fields, "register")?,
+        registers: number_field(fields, "registers")?,
+        count: number_field(fields, "count")?,
+        state: ProofState::Pending,
+    };
+    // The hash is part of the storage keys, anything else could write next to another module or into the owner index.
+    if !is_sha256_hex(&proof.wasm_hash) {
+        return Err(AppError::invalid_field(
+            "wasm_hash",
+            "`wasm_hash` must be the SHA-256 of a module, in hex",
+        ));
+    }
+    if proof.registers == 0 {
+        return Err(AppError::invalid_field("registers", "`registers` must be at least 1"));
+    }
+    if proof.register >= proof.registers {
+        return Err(AppError::invalid_field(
+            "register",
+            format!("`register` must be below `registers` ({})", proof.registers),
+        ));
+    }
+    if proof.count == 0 {
+        return Err(AppError::invalid_field("count", "`count` must be at least 1"));
+    }
+    Ok(proof)
+}
+
+// The GitHub token in an `Authorization: Bearer` header, if the request has one. Any other scheme is refused rather
+// than silently storing the proof as anonymous.
+fn bearer_token(headers: &HeaderMap) -> Result<Option<&str>, AppError> {
+    let Some(value) = headers.get(header::AUTHORIZATION) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(Some)
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::UNAUTHORIZED,
+                "Authorization must be `Bearer <GitHub token>`",
+            )
+        })
+}
+
+// The configured binding, or `default` when it is unset or blank.
+fn bucket_binding(configured: Option<String>, default: &str) -> String {
+    configured
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
+
+fn bucket(env: &Env, var: &str, default: &str) -> Result<worker::Bucket, AppError> {
+    let binding = bucket_binding(env.var(var).ok().map(|name| name.to_string()), default);
+    env.bucket(&binding).map_err(|_| AppError::missing_bucket(&binding))
+}
+
+/// The R2 bucket holding modules, bound as `wasm` unless `WASM_BUCKET` names another binding.
+pub fn wasm_bucket(env: &Env) -> Result<worker::Bucket, AppError> {
+    bucket(env, "WASM_BUCKET", "wasm")
+}
+
+/// The R2 bucket holding proofs, bound as `proofs` unless `PROOFS_BUCKET` names another binding.
+pub fn proofs_bucket(env: &Env) -> Result<worker::Bucket, AppError> {
+    bucket(env, "PROOFS_BUCKET", "proofs")
+}
+
+// Proofs uploaded with a GitHub token belong to that user, the rest are anonymous
+async fn proof_owner(api_url: &str, headers: &HeaderMap) -> Result<ProofOwner, AppError> {
+    let Some(token) = bearer_token(headers)? else {
+        return Ok(ProofOwner::Anonymous);
+    };
+    Ok(ProofOwner::GithubId(github_user_id(api_url, token).await?))
+}
+
+/// The id of the GitHub user `token` belongs to.
+pub async fn github_user_id(api_url: &str, token: &str) -> Result<u64, AppError> {
+    let github = GithubClient::new(api_url, UserAccessToken::from_string(token.to_string()));
+    match github.current_user().await {
+        Ok(user) => Ok(user.id),
+        Err(GithubError::Unauthorized) => Err(AppError::new(StatusCode::UNAUTHORIZED, "Invalid GitHub token")),
+        Err(e @ GithubError::Timeout) => Err(AppError::new(StatusCode::GATEWAY_TIMEOUT, e.to_string())),
+        Err(e) => Err(AppError::new(
+            StatusCode::BAD_GATEWAY,
+            format!("Could not look up the GitHub user: {}", e),
+        )),
+    }
+}
+
+async fn upload_proof(
+    wasm: &impl Storage,
+    proofs: &impl Storage,
+    request_id: &RequestId,
+    mut proof: Proof,
+) -> Result<(StatusCode, String), AppError> {
+    let proof_hash = proof.content_hash();
+    let key = format!("{}/{}", proof.wasm_hash, proof_hash);
+    // We're only interested in new proofs.
+    if proofs.head(&key).await? {
+        return Err(AppError::new(StatusCode::CONFLICT, "Proof already exists"));
+    }
+
+    let wasm_object = wasm
+        .get(&proof.wasm_hash)
         .await?
-        .context("WASM not found")?
-        .body()
-        .context("R2 object without body")?
-        .bytes()
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "WASM not found"))?;
+    // The run is fuel metered, so a module that traps or loops on this seed rejects the proof instead of hanging. It
+    // checks `hash` only: `count` and the register fields describe the uploader's search over many seeds, which one run
+    // can't reproduce. They are part of the content hash, so they can't be changed once the proof is stored.
+    proof.state = match crate::wasm::run_test(&wasm_object, "test", proof.seed) {
+        Ok(result) if result == proof.hash => ProofState::Verified,
+        Ok(result) => {
+            log::info!(
+                "[{}] Invalid proof for {}: seed {} gives {}, not {}",
+                request_id,
+                proof.wasm_hash,
+                proof.seed,
+                result,
+                proof.hash
+            );
+            ProofState::Rejected
+        }
+        Err(e) => {
+            log::info!("[{}] Failed to run WASM {}: {:#}", request_id, proof.wasm_hash, e);
+            ProofState::Rejected
+        }
+    };
+
+    proofs.put(&key, serde_json::to_vec(&proof)?).await?;
+    if let Some(index_key) = owner_index_key(&proof.owner, &key) {
+        proofs.put(&index_key, Vec::new()).await?;
+    }
+    log::info!("[{}] Stored {:?} proof {}", request_id, proof.state, key);
+    if proof.state != ProofState::Verified {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Invalid proof"));
+    }
+    Ok((StatusCode::CREATED, proof_hash))
+}
+
+// Empty object under `by-owner/{id}/{wasm_hash}/{proof_hash}` that lets R2 list the proofs of a GitHub user by
+// prefix. Anonymous proofs can't be asked for by owner, so they go without.
+fn owner_index_key(owner: &ProofOwner, key: &str) -> Option<String> {
+    match owner {
+        ProofOwner::Anonymous => None,
+        ProofOwner::GithubId(id) => Some(format!("{}/{}/{}", OWNER_INDEX_PREFIX, id, key)),
+    }
+}
+
+// Verification status of a single proof, as recorded with it at upload. A proof that isn't stored (yet) is pending.
+#[axum::debug_handler]
+#[worker::send]
+pub async fn proof_status_handler(
+    Extension(env): Extension<Arc<Env>>,
+    Path((wasm_hash, proof_hash)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    proof_status(&proofs_bucket(&env)?, wasm_hash, proof_hash).await
+}
+
+/// The proof stored under `proof_hash` for the module `wasm_hash`, if there is one.
+pub async fn find_proof(proofs: &impl Storage, wasm_hash: &str, proof_hash: &str) -> anyhow::Result<Option<Proof>> {
+    let key = format!("{}/{}", wasm_hash, proof_hash);
+    let Some(data) = proofs.get(&key).await? else {
+        return Ok(None);
+    };
+    let proof = serde_json::from_slice(&data).with_context(|| format!("Failed to parse proof {}", key))?;
+    Ok(Some(proof))
+}
+
+async fn proof_status(
+    proofs: &impl Storage,
+    wasm_hash: String,
+    proof_hash: String,
+) -> Result<Json<ProofStatusResponse>, AppError> {
+    let Some(proof) = find_proof(proofs, &wasm_hash, &proof_hash).await? else {
+        return Ok(Json(ProofStatusResponse {
+            state: ProofState::Pending,
+            result: None,
+        }));
+    };
+    Ok(Json(ProofStatusResponse {
+        state: proof.state,
+        result: (proof.state == ProofState::Verified).then_some(proof.hash),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct ListProofsParams {
+    limit: Option<u32>,
+    cursor: Option<String>,
+    /// Only proofs created at or after this time, in milliseconds since the Unix epoch.
+    since: Option<u64>,
+    /// Only proofs created at or before this time, in milliseconds since the Unix epoch.
+    until: Option<u64>,
+}
+
+impl ListProofsParams {
+    // The creation times asked for, both ends included. A `400` when `since` is after `until`.
+    fn created_range(&self) -> Result<RangeInclusive<u64>, AppError> {
+        let since = self.since.unwrap_or(u64::MIN);
+        let until = self.until.unwrap_or(u64::MAX);
+        if since > until {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                format!("`since` ({}) must not be after `until` ({})", since, until),
+            ));
+        }
+        Ok(since..=until)
+    }
+}
+
+// Lists the proofs stored for a module, a page at a time and newest first within a page. `since` and `until` filter
+// each page after it is read, so a page can come back shorter than `limit`, or empty, with more to follow behind its
+// cursor.
+#[axum::debug_handler]
+#[worker::send]
+pub async fn list_proofs_handler(
+    Extension(env): Extension<Arc<Env>>,
+    Path(wasm_hash): Path<String>,
+    Query(params): Query<ListProofsParams>,
+) -> Result<impl IntoResponse, AppError> {
+    list_proofs(&proofs_bucket(&env)?, &wasm_hash, params).await
+}
+
+async fn list_proofs(
+    proofs: &impl Storage,
+    wasm_hash: &str,
+    params: ListProofsParams,
+) -> Result<Json<ProofListResponse>, AppError> {
+    // Only module hashes name a folder of proofs. Anything else, like `by-owner`, would list keys that aren't proofs.
+    if !is_sha256_hex(wasm_hash) {
+        return Err(AppError::new(StatusCode::BAD_REQUEST, "Not a module hash"));
+    }
+    let created = params.created_range()?;
+    let mut page = list_proofs_page(proofs, wasm_hash, params.limit, params.cursor).await?;
+    page.proofs.retain(|proof| created.contains(&proof.created_at));
+    Ok(Json(page))
+}
+
+// Newest first, proofs created at the same time ordered by their content hash.
+fn sort_newest_first(proofs: &mut [Proof]) {
+    proofs.sort_by_cached_key(|proof| (std::cmp::Reverse(proof.created_at), proof.content_hash()));
+}
+
+/// One page of the proofs stored for a module, `limit` defaulting to 100 and capped at what R2 returns per call. The
+/// proofs of a page are newest first, see [`sort_newest_first`]. Pages themselves follow R2's key order, so a later
+/// page can hold newer proofs than an earlier one.
+pub async fn list_proofs_page(
+    bucket: &impl Storage,
+    wasm_hash: &str,
+    limit: Option<u32>,
+    cursor: Option<String>,
+) -> anyhow::Result<ProofListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_PROOF_LIST_LIMIT).clamp(1, MAX_PROOF_LIST_LIMIT);
+    let page = bucket.list(&format!("{}/", wasm_hash), limit, cursor).await?;
+
+    let mut proofs = try_join_all(page.keys.iter().map(|key| async move {
+        let data = bucket
+            .get(key)
+            .await?
+            .with_context(|| format!("Proof {} disappeared while listing", key))?;
+        serde_json::from_slice::<Proof>(&data).with_context(|| format!("Failed to parse proof {}", key))
+    }))
+    .await?;
+    sort_newest_first(&mut proofs);
+
+    Ok(ProofListResponse {
+        proofs,
+        cursor: page.cursor,
+    })
+}
+
+/// The proofs owned by the GitHub user `id`, newest first, found through the index [`upload_proof`] keeps next to
+/// them. Proofs stored before there was an index don't show up.
+pub async fn list_owner_proofs(bucket: &impl Storage, id: u64) -> anyhow::Result<Vec<Proof>> {
+    let prefix = format!("{}/{}/", OWNER_INDEX_PREFIX, id);
+    let mut owned = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = bucket.list(&prefix, MAX_PROOF_LIST_LIMIT, cursor).await?;
+        let proofs = try_join_all(page.keys.iter().map(|index_key| {
+            let prefix = &prefix;
+            async move {
+                let key = index_key
+                    .strip_prefix(prefix.as_str())
+                    .with_context(|| format!("Index key {} outside of {}", index_key, prefix))?;
+                let data = bucket
+                    .get(key)
+                    .await?
+                    .with_context(|| format!("Proof {} is indexed but not stored", key))?;
+                serde_json::from_slice::<Proof>(&data).with_context(|| format!("Failed to parse proof {}", key))
+            }
+        }))
         .await?;
-    let result = crate::wasm::run_test(&wasm_object, "test", params.seed).context("Failed to run WASM")?;
-    // check that result == params.hash
-    if result != params.hash {
-        return Err(AppError((StatusCode::BAD_REQUEST, "Invalid proof").into_response()));
-    }
-    let d1 = env.d1("pot")?;
-    // Insert the proof into the database. If the seed or hash already exist, return a 204. We're only interested in new proofs.
-    let ret = query!(
-        &d1,
-        "INSERT INTO pot (wasm, seed, hash) VALUES (?, ?, ?)",
-        &params.wasm,
-        params.seed,
-        params.hash
-    )?
-    .run()
-    .await
-    .map_err(|_| AppError((StatusCode::NO_CONTENT, String::default()).into_response()))?;
-    log::info!("D1 result: {:?} {:?}", ret.success(), ret.error());
-    Ok(StatusCode::CREATED)
-    // Fields: wasm, created_at, seed, hash, owner
+        owned.extend(proofs);
+
+        cursor = page.cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    sort_newest_first(&mut owned);
+    Ok(owned)
+}
+
+// The scheme, host and port of `url`, which is all a CSP source needs.
+fn origin(url: &str) -> String {
+    url::Url::parse(url).map_or_else(|_| url.to_string(), |url| url.origin().ascii_serialization())
+}
+
+/// Policy sent with every HTML page. Scripts and styles are inline for hydration, images may come from GitHub
+/// avatars, and fetches go to this origin or the GitHub API the browser build talks to.
+pub fn content_security_policy() -> String {
+    format!(
+        concat!(
+            "default-src 'self'; ",
+            "script-src 'self' 'unsafe-inline' 'wasm-unsafe-eval'; ",
+            "style-src 'self' 'unsafe-inline'; ",
+            // Enterprise servers serve avatars from their own host.
+            "img-src 'self' https://avatars.githubusercontent.com {}; ",
+            "connect-src 'self' {}; ",
+            "frame-ancestors 'none'; ",
+            "base-uri 'self'; ",
+            "form-action 'self'"
+        ),
+        origin(github_web_url()),
+        origin(github_api_url()),
+    )
+}
+
+// Adds the security headers to HTML responses, API and module responses are left as they are
+pub async fn security_headers(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    if is_html {
+        let headers = response.headers_mut();
+        headers.insert(
+            header::CONTENT_SECURITY_POLICY,
+            header::HeaderValue::from_str(&content_security_policy()).expect("policy is a valid header value"),
+        );
+        headers.insert(
+            header::X_CONTENT_TYPE_OPTIONS,
+            header::HeaderValue::from_static("nosniff"),
+        );
+        headers.insert(
+            header::REFERRER_POLICY,
+            header::HeaderValue::from_static("strict-origin-when-cross-origin"),
+        );
+        headers.insert(header::X_FRAME_OPTIONS, header::HeaderValue::from_static("DENY"));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    // The request id is taken from `cf-ray` when Cloudflare sets it, and generated otherwise
+    #[test]
+    fn request_id_unit_test_1() {
+        let mut headers = HeaderMap::new();
+        headers.insert("cf-ray", "8f1c2a3b4d5e6f70-AMS".parse().unwrap());
+        assert_eq!(RequestId::from_headers(&headers).to_string(), "8f1c2a3b4d5e6f70-AMS");
+
+        let now = 1_700_000_000_000;
+        let first = RequestId::generate(now).to_string();
+        let second = RequestId::generate(now).to_string();
+        assert_eq!(first.len(), 8);
+        assert_ne!(first, second);
+    }
+
+    // Cached results are keyed by module, export, argument and validator version
+    #[test]
+    fn validation_cache_key_unit_test_1() {
+        let hash = sha256_hex(b"module");
+        assert_eq!(
+            validation_cache_key(&hash, "test", 42),
+            format!("{}.test.42.v{}.result.json", hash, VALIDATOR_VERSION)
+        );
+        assert_ne!(
+            validation_cache_key(&hash, "test", 42),
+            format!("{}.test.42.result.json", hash)
+        );
+        assert_ne!(
+            validation_cache_key(&hash, "test", 42),
+            validation_cache_key(&hash, "test", 43)
+        );
+        assert!(!is_sha256_hex(&validation_cache_key(&hash, "test", 42)));
+    }
+
+    // A module stored under its SHA-256 comes back byte for byte from the download route
+    #[test]
+    fn download_wasm_unit_test_1() {
+        let module = wat::parse_str(r#"(module (func (export "test") (param i64) (result i64) local.get 0))"#).unwrap();
+        let hash = sha256_hex(&module);
+        let storage = MemoryStorage::default();
+        storage.insert(hash.clone(), module.clone());
+
+        let response = futures::executor::block_on(download_wasm(&storage, hash.clone(), None))
+            .ok()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ETAG], format!("\"{}\"", hash).as_str());
+        assert_eq!(response.headers()[header::CACHE_CONTROL], IMMUTABLE_CACHE_CONTROL);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/wasm");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            format!("attachment; filename=\"{}.wasm\"", hash).as_str()
+        );
+        let body = futures::executor::block_on(axum::body::to_bytes(response.into_body(), usize::MAX)).unwrap();
+        assert_eq!(body, module);
+        assert_eq!(sha256_hex(&body), hash);
+    }
+
+    // Unknown hashes and keys that aren't hashes are both not found
+    #[test]
+    fn download_wasm_unit_test_2() {
+        let storage = MemoryStorage::default();
+        storage.insert("not-a-hash", b"\0asm".to_vec());
+        for key in [sha256_hex(b"missing"), "not-a-hash".to_string()] {
+            let response = match futures::executor::block_on(download_wasm(&storage, key, None)) {
+                Ok(_) => panic!("expected an error"),
+                Err(e) => e.into_response(),
+            };
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    // A client that already has the module gets a 304 without a body, a stale or missing tag gets the module
+    #[test]
+    fn download_wasm_unit_test_3() {
+        let module = identity_module();
+        let hash = sha256_hex(&module);
+        let storage = MemoryStorage::default();
+        storage.insert(hash.clone(), module);
+        let etag = format!("\"{}\"", hash);
+        let download = |if_none_match: Option<&str>| {
+            futures::executor::block_on(download_wasm(&storage, hash.clone(), if_none_match))
+                .ok()
+                .unwrap()
+        };
+
+        for if_none_match in [
+            etag.clone(),
+            format!("W/{}", etag),
+            format!("\"other\", {}", etag),
+            "*".into(),
+        ] {
+            let response = download(Some(&if_none_match));
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+            assert_eq!(response.headers()[header::ETAG], etag.as_str());
+            let body = futures::executor::block_on(axum::body::to_bytes(response.into_body(), usize::MAX)).unwrap();
+            assert!(body.is_empty());
+        }
+        assert_eq!(download(Some("\"other\"")).status(), StatusCode::OK);
+        assert_eq!(download(None).status(), StatusCode::OK);
+    }
+
+    // The version reported is the crate's own
+    #[test]
+    fn version_unit_test_1() {
+        let Json(version) = futures::executor::block_on(version_handler());
+        assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+        assert!(!version.git_sha.is_empty());
+    }
+
+    // Proofs without an Authorization header are anonymous, a header that isn't a bearer token is refused
+    #[test]
+    fn bearer_token_unit_test_1() {
+        let with = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+            headers
+        };
+        assert_eq!(bearer_token(&HeaderMap::new()).ok().unwrap(), None);
+        assert_eq!(
+            bearer_token(&with("Bearer gho_abc123")).ok().unwrap(),
+            Some("gho_abc123")
+        );
+        assert_eq!(
+            error_status(bearer_token(&with("Basic dXNlcjpwYXNz"))),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(error_status(bearer_token(&with("Bearer "))), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            futures::executor::block_on(proof_owner(crate::github::GITHUB_API, &HeaderMap::new()))
+                .ok()
+                .unwrap(),
+            ProofOwner::Anonymous
+        );
+    }
+
+    fn identity_module() -> Vec<u8> {
+        wat::parse_str(r#"(module (func (export "test") (param i64) (result i64) local.get 0))"#).unwrap()
+    }
+
+    fn request_id() -> RequestId {
+        RequestId("test".to_string())
+    }
+
+    fn multipart_request(uri: &str, fields: &[(&str, &[u8])]) -> http::Request<axum::body::Body> {
+        let mut body = Vec::new();
+        for (name, data) in fields {
+            body.extend_from_slice(
+                format!(
+                    "--BOUNDARY\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\r\n",
+                    name, name
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(b"--BOUNDARY--\r\n");
+        http::Request::post(uri)
+            .header(header::CONTENT_TYPE, "multipart/form-data; boundary=BOUNDARY")
+            .body(axum::body::Body::from(body))
+            .unwrap()
+    }
+
+    fn multipart(fields: &[(&str, &[u8])]) -> Multipart {
+        use axum::extract::FromRequest;
+
+        let request = multipart_request("/upload_wasm", fields);
+        futures::executor::block_on(Multipart::from_request(request, &())).unwrap()
+    }
+
+    fn error_status<T>(result: Result<T, AppError>) -> StatusCode {
+        match result {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e.into_response().status(),
+        }
+    }
+
+    // Uploading a module stores it under its hash, and uploading it again leaves it alone
+    #[test]
+    fn upload_wasm_unit_test_1() {
+        let module = identity_module();
+        let storage = MemoryStorage::default();
+
+        let Json(first) = futures::executor::block_on(upload_wasm(&storage, &request_id(), module.clone()))
+            .ok()
+            .unwrap();
+        assert_eq!(first.hash, sha256_hex(&module));
+        assert_eq!(first.size, module.len());
+        assert!(!first.already_existed);
+        assert_eq!(
+            futures::executor::block_on(storage.get(&first.hash)).unwrap(),
+            Some(module.clone())
+        );
+
+        let Json(second) = futures::executor::block_on(upload_wasm(&storage, &request_id(), module))
+            .ok()
+            .unwrap();
+        assert_eq!(second.hash, first.hash);
+        assert!(second.already_existed);
+    }
+
+    // Reading the upload in chunks hashes it the same as hashing the whole module, and other fields are skipped
+    #[test]
+    fn upload_wasm_unit_test_3() {
+        let module = identity_module();
+        let mut payload = multipart(&[("note", b"not the module"), ("file", &module)]);
+        let (data, hash) = futures::executor::block_on(read_wasm_upload(&mut payload))
+            .ok()
+            .unwrap();
+        assert_eq!(data, module);
+        assert_eq!(hash, sha256_hex(&module));
+
+        let storage = MemoryStorage::default();
+        let Json(stored) = futures::executor::block_on(store_wasm(&storage, &request_id(), data, hash))
+            .ok()
+            .unwrap();
+        assert_eq!(stored.hash, sha256_hex(&module));
+    }
+
+    // An upload without a `file` field names the missing field in the JSON error
+    #[test]
+    fn upload_wasm_unit_test_4() {
+        for mut payload in [multipart(&[]), multipart(&[("module", b"\0asm")])] {
+            let response = match futures::executor::block_on(read_wasm_upload(&mut payload)) {
+                Ok(_) => panic!("expected an error"),
+                Err(e) => e.into_response(),
+            };
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = futures::executor::block_on(axum::body::to_bytes(response.into_body(), usize::MAX)).unwrap();
+            assert_eq!(
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+                serde_json::json!({ "error": "missing_field", "field": "file" })
+            );
+        }
+    }
+
+    // Files that aren't modules, or lack a usable `test` export, are not stored
+    #[test]
+    fn upload_wasm_unit_test_2() {
+        let storage = MemoryStorage::default();
+        assert_eq!(
+            error_status(futures::executor::block_on(upload_wasm(
+                &storage,
+                &request_id(),
+                b"hello".to_vec()
+            ))),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+        let empty = wat::parse_str("(module)").unwrap();
+        assert_eq!(
+            error_status(futures::executor::block_on(upload_wasm(
+                &storage,
+                &request_id(),
+                empty.clone()
+            ))),
+            StatusCode::BAD_REQUEST
+        );
+        assert!(!futures::executor::block_on(storage.head(&sha256_hex(&empty))).unwrap());
+    }
+
+    fn proof_for(wasm_hash: &str, seed: u64, hash: u64) -> Proof {
+        Proof {
+            wasm_hash: wasm_hash.to_string(),
+            owner: ProofOwner::Anonymous,
+            created_at: 1_700_000_000_000,
+            seed,
+            hash,
+            weight: 3,
+            register: 7,
+            registers: 64,
+            count: 1,
+            state: ProofState::Pending,
+        }
+    }
+
+    // A proof that checks out is stored as verified, and can't be uploaded twice
+    #[test]
+    fn upload_proof_unit_test_1() {
+        let module = identity_module();
+        let wasm_hash = sha256_hex(&module);
+        let (wasm, proofs) = (MemoryStorage::default(), MemoryStorage::default());
+        wasm.insert(wasm_hash.clone(), module);
+
+        let proof = proof_for(&wasm_hash, 42, 42);
+        let (status, proof_hash) =
+            futures::executor::block_on(upload_proof(&wasm, &proofs, &request_id(), proof.clone()))
+                .ok()
+                .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(proof_hash, sha256_hex(proof.content()));
+
+        let Json(status) = futures::executor::block_on(proof_status(&proofs, wasm_hash.clone(), proof_hash))
+            .ok()
+            .unwrap();
+        assert_eq!(status.state, ProofState::Verified);
+        assert_eq!(status.result, Some(42));
+
+        assert_eq!(
+            error_status(futures::executor::block_on(upload_proof(
+                &wasm,
+                &proofs,
+                &request_id(),
+                proof
+            ))),
+            StatusCode::CONFLICT
+        );
+    }
+
+    // A proof whose hash doesn't match is refused but kept as rejected, and unknown modules are not found
+    #[test]
+    fn upload_proof_unit_test_2() {
+        let module = identity_module();
+        let wasm_hash = sha256_hex(&module);
+        let (wasm, proofs) = (MemoryStorage::default(), MemoryStorage::default());
+        wasm.insert(wasm_hash.clone(), module);
+
+        let proof = proof_for(&wasm_hash, 42, 43);
+        assert_eq!(
+            error_status(futures::executor::block_on(upload_proof(
+                &wasm,
+                &proofs,
+                &request_id(),
+                proof.clone()
+            ))),
+            StatusCode::BAD_REQUEST
+        );
+        let Json(status) =
+            futures::executor::block_on(proof_status(&proofs, wasm_hash.clone(), sha256_hex(proof.content())))
+                .ok()
+                .unwrap();
+        assert_eq!(status.state, ProofState::Rejected);
+        assert_eq!(status.result, None);
+
+        let unknown = proof_for(&sha256_hex(b"missing"), 42, 42);
+        assert_eq!(
+            error_status(futures::executor::block_on(upload_proof(
+                &wasm,
+                &proofs,
+                &request_id(),
+                unknown
+            ))),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    // A proof with a GitHub owner is stored under its content key and indexed under its owner, an anonymous one isn't
+    // indexed
+    #[test]
+    fn upload_proof_unit_test_3() {
+        let module = identity_module();
+        let wasm_hash = sha256_hex(&module);
+        let (wasm, proofs) = (MemoryStorage::default(), MemoryStorage::default());
+        wasm.insert(wasm_hash.clone(), module);
+
+        let owned = Proof {
+            owner: ProofOwner::GithubId(1234),
+            ..proof_for(&wasm_hash, 42, 42)
+        };
+        let (_, proof_hash) = futures::executor::block_on(upload_proof(&wasm, &proofs, &request_id(), owned))
+            .ok()
+            .unwrap();
+        let key = format!("{}/{}", wasm_hash, proof_hash);
+        let index_key = format!("by-owner/1234/{}/{}", wasm_hash, proof_hash);
+        assert!(futures::executor::block_on(proofs.head(&key)).unwrap());
+        assert_eq!(
+            futures::executor::block_on(proofs.get(&index_key)).unwrap(),
+            Some(Vec::new())
+        );
+
+        let (_, anonymous_hash) =
+            futures::executor::block_on(upload_proof(&wasm, &proofs, &request_id(), proof_for(&wasm_hash, 7, 7)))
+                .ok()
+                .unwrap();
+        assert!(futures::executor::block_on(proofs.head(&format!("{}/{}", wasm_hash, anonymous_hash))).unwrap());
+        assert_eq!(owner_index_key(&ProofOwner::Anonymous, &key), None);
+    }
+
+    // Only the indexed proofs of the owner are listed, newest first, and a dangling index entry is an error
+    #[test]
+    fn list_owner_proofs_unit_test_1() {
+        let module = identity_module();
+        let wasm_hash = sha256_hex(&module);
+        let (wasm, proofs) = (MemoryStorage::default(), MemoryStorage::default());
+        wasm.insert(wasm_hash.clone(), module);
+
+        let owned = |owner, seed, created_at| Proof {
+            owner,
+            created_at,
+            ..proof_for(&wasm_hash, seed, seed)
+        };
+        let uploads = [
+            owned(ProofOwner::GithubId(1234), 1, 1_700_000_000_000),
+            owned(ProofOwner::GithubId(1234), 2, 1_700_000_002_000),
+            owned(ProofOwner::GithubId(5678), 3, 1_700_000_001_000),
+            owned(ProofOwner::Anonymous, 4, 1_700_000_003_000),
+        ];
+        for proof in uploads {
+            futures::executor::block_on(upload_proof(&wasm, &proofs, &request_id(), proof))
+                .ok()
+                .unwrap();
+        }
+
+        let listed = futures::executor::block_on(list_owner_proofs(&proofs, 1234)).unwrap();
+        assert_eq!(listed.iter().map(|proof| proof.seed).collect::<Vec<_>>(), vec![2, 1]);
+        assert!(listed.iter().all(|proof| proof.state == ProofState::Verified));
+        assert!(futures::executor::block_on(list_owner_proofs(&proofs, 42))
+            .unwrap()
+            .is_empty());
+
+        proofs.insert(format!("by-owner/1234/{}/missing", wasm_hash), Vec::new());
+        let error = futures::executor::block_on(list_owner_proofs(&proofs, 1234)).unwrap_err();
+        assert!(error.to_string().contains("is indexed but not stored"));
+    }
+
+    // Only HTML responses get the security headers
+    #[test]
+    fn security_headers_unit_test_1() {
+        use axum::body::Body;
+        use axum::response::Html;
+        use axum::routing::get;
+        use tower::ServiceExt;
+
+        let app = axum::Router::new()
+            .route("/", get(|| async { Html("<p>hi</p>") }))
+            .route("/api", get(|| async { AppError::new(StatusCode::NOT_FOUND, "nope") }))
+            .layer(axum::middleware::from_fn(security_headers));
+        let get = |uri: &'static str| {
+            let app = app.clone();
+            futures::executor::block_on(app.oneshot(http::Request::get(uri).body(Body::empty()).unwrap())).unwrap()
+        };
+
+        let page = get("/");
+        let policy = content_security_policy();
+        assert_eq!(page.headers()[header::CONTENT_SECURITY_POLICY], policy.as_str());
+        assert!(policy.contains("img-src 'self' https://avatars.githubusercontent.com https://github.com;"));
+        assert!(policy.contains("connect-src 'self' https://api.github.com;"));
+        assert_eq!(page.headers()[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(page.headers()[header::X_FRAME_OPTIONS], "DENY");
+        assert!(page.headers().contains_key(header::REFERRER_POLICY));
+
+        let api = get("/api");
+        assert!(!api.headers().contains_key(header::CONTENT_SECURITY_POLICY));
+    }
+
+    fn call(app: Router, request: http::Request<axum::body::Body>) -> Response {
+        use tower::ServiceExt;
+
+        futures::executor::block_on(app.oneshot(request)).unwrap()
+    }
+
+    fn json_body<T: serde::de::DeserializeOwned>(response: Response) -> T {
+        let bytes = futures::executor::block_on(axum::body::to_bytes(response.into_body(), usize::MAX)).unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    // Health and version answer without a worker behind them
+    #[test]
+    fn api_routes_unit_test_1() {
+        let get = |uri| http::Request::get(uri).body(axum::body::Body::empty()).unwrap();
+
+        let health = call(storage_routes(), get("/health"));
+        assert_eq!(health.status(), StatusCode::OK);
+        let health: HealthResponse = json_body(health);
+        assert_eq!(health.status, "ok");
+        assert!(!health.wasm_bucket);
+
+        let version = call(stateless_routes(), get("/version"));
+        assert_eq!(version.status(), StatusCode::OK);
+        let version: VersionResponse = json_body(version);
+        assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+
+        assert_eq!(call(stateless_routes(), get("/health")).status(), StatusCode::NOT_FOUND);
+        assert_eq!(call(storage_routes(), get("/version")).status(), StatusCode::NOT_FOUND);
+
+        let metrics = call(stateless_routes(), get("/metrics"));
+        assert_eq!(metrics.status(), StatusCode::OK);
+        assert_eq!(metrics.headers()[header::CONTENT_TYPE], metrics::CONTENT_TYPE);
+    }
+
+    // `/upload_wasm` through the router, against in-memory storage: the module is stored and the response is the
+    // upload JSON, while a request without a `file` field gets the missing field error
+    #[test]
+    fn api_routes_unit_test_2() {
+        let module = identity_module();
+        let storage = Arc::new(MemoryStorage::default());
+        let routes = || storage_routes().layer(Extension(storage.clone()));
+        // With a `cf-ray` header the request id doesn't need the JavaScript clock.
+        let upload = |fields: &[(&str, &[u8])]| {
+            let mut request = multipart_request("/upload_wasm", fields);
+            request
+                .headers_mut()
+                .insert("cf-ray", "8f1c2a3b4d5e6f70-AMS".parse().unwrap());
+            request
+        };
+
+        let response = call(routes(), upload(&[("note", b"hello"), ("file", &module)]));
+        assert_eq!(response.status(), StatusCode::OK);
+        let uploaded: UploadWasmResponse = json_body(response);
+        assert_eq!(uploaded.hash, sha256_hex(&module));
+        assert_eq!(uploaded.size, module.len());
+        assert!(futures::executor::block_on(storage.head(&uploaded.hash)).unwrap());
+
+        let response = call(routes(), upload(&[("func", b"test")]));
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let error: ErrorResponse = json_body(response);
+        assert_eq!(error.error, "missing_field");
+        assert_eq!(error.field.as_deref(), Some("file"));
+    }
+
+    fn proof_fields(overrides: &[(&str, &str)]) -> HashMap<String, String> {
+        let mut fields = [
+            (
+                "wasm_hash",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            ("seed", "42"),
+            ("hash", "20015998343868"),
+            ("weight", "3"),
+            ("register", "7"),
+            ("registers", "64"),
+            ("count", "1"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect::<HashMap<_, _>>();
+        for (name, value) in overrides {
+            fields.insert(name.to_string(), value.to_string());
+        }
+        fields
+    }
+
+    // A well-formed upload becomes a pending proof, surrounding whitespace is ignored
+    #[test]
+    fn proof_fields_unit_test_1() {
+        let proof = proof_from_fields(
+            &proof_fields(&[("seed", " 18446744073709551615\n")]),
+            ProofOwner::Anonymous,
+            7,
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(proof.seed, u64::MAX);
+        assert_eq!(proof.hash, 20015998343868);
+        assert_eq!(
+            (proof.weight, proof.register, proof.registers, proof.count),
+            (3, 7, 64, 1)
+        );
+        assert_eq!(proof.created_at, 7);
+        assert_eq!(proof.state, ProofState::Pending);
+    }
+
+    // Non-numeric, negative, overflowing and out-of-range values, and module hashes that aren't one, are a 400 naming
+    // the field
+    #[test]
+    fn proof_fields_unit_test_2() {
+        let cases = [
+            ("seed", "forty-two"),
+            ("seed", "18446744073709551616"),
+            ("hash", "-1"),
+            ("weight", "4294967296"),
+            ("register", "1.5"),
+            ("registers", "0"),
+            ("register", "64"),
+            ("count", "0"),
+            ("count", ""),
+            ("wasm_hash", "by-owner"),
+            (
+                "wasm_hash",
+                "by-owner/1234/ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+        ];
+        for (name, value) in cases {
+            let error = proof_from_fields(&proof_fields(&[(name, value)]), ProofOwner::Anonymous, 0)
+                .err()
+                .unwrap();
+            assert_eq!(error.status, StatusCode::BAD_REQUEST, "{} = {:?}", name, value);
+            assert_eq!(error.field.as_deref(), Some(name), "{} = {:?}", name, value);
+        }
+
+        let mut fields = proof_fields(&[]);
+        fields.remove("count");
+        let error = proof_from_fields(&fields, ProofOwner::Anonymous, 0).err().unwrap();
+        assert_eq!(
+            (error.message.as_str(), error.field.as_deref()),
+            ("missing_field", Some("count"))
+        );
+    }
+
+    fn list_params(query: &str) -> Result<ListProofsParams, String> {
+        let uri = format!("/proofs/abc?{}", query).parse::<http::Uri>().unwrap();
+        Query::<ListProofsParams>::try_from_uri(&uri)
+            .map(|Query(params)| params)
+            .map_err(|e| e.body_text())
+    }
+
+    // `since` and `until` both include proofs created exactly at them, and either can be left out
+    #[test]
+    fn list_proofs_params_unit_test_1() {
+        let created = list_params("since=1000&until=2000")
+            .unwrap()
+            .created_range()
+            .ok()
+            .unwrap();
+        assert!(!created.contains(&999));
+        assert!(created.contains(&1000));
+        assert!(created.contains(&2000));
+        assert!(!created.contains(&2001));
+
+        let created = list_params("since=1000&until=1000")
+            .unwrap()
+            .created_range()
+            .ok()
+            .unwrap();
+        assert!(created.contains(&1000));
+        assert!(!created.contains(&1001));
+
+        let created = list_params("until=5").unwrap().created_range().ok().unwrap();
+        assert!(created.contains(&0) && created.contains(&5) && !created.contains(&6));
+        let created = list_params("limit=10").unwrap().created_range().ok().unwrap();
+        assert!(created.contains(&0) && created.contains(&u64::MAX));
+    }
+
+    // `since` after `until` is a `400`, and so is a time that isn't a number
+    #[test]
+    fn list_proofs_params_unit_test_2() {
+        let error = list_params("since=2001&until=2000")
+            .unwrap()
+            .created_range()
+            .err()
+            .unwrap();
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.message, "`since` (2001) must not be after `until` (2000)");
+        assert!(list_params("since=yesterday").is_err());
+    }
+
+    // A page is newest first, with ties broken by proof hash, and only holds proofs of the module asked for
+    #[test]
+    fn list_proofs_page_unit_test_1() {
+        let wasm_hash = sha256_hex(b"module");
+        let storage = MemoryStorage::default();
+        let stored = [(1, 1_000), (2, 3_000), (3, 2_000), (4, 3_000), (5, 2_000)].map(|(seed, created_at)| Proof {
+            created_at,
+            ..proof_for(&wasm_hash, seed, seed)
+        });
+        for proof in &stored {
+            storage.insert(
+                format!("{}/{}", wasm_hash, proof.content_hash()),
+                serde_json::to_vec(proof).unwrap(),
+            );
+        }
+        let other = proof_for(&sha256_hex(b"other"), 6, 6);
+        storage.insert(
+            format!("{}/{}", other.wasm_hash, other.content_hash()),
+            serde_json::to_vec(&other).unwrap(),
+        );
+
+        let page = futures::executor::block_on(list_proofs_page(&storage, &wasm_hash, None, None)).unwrap();
+        assert_eq!(page.cursor, None);
+        let order = page
+            .proofs
+            .iter()
+            .map(|proof| (proof.created_at, proof.content_hash()))
+            .collect::<Vec<_>>();
+        let mut expected = stored
+            .iter()
+            .map(|proof| (proof.created_at, proof.content_hash()))
+            .collect::<Vec<_>>();
+        expected.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        assert_eq!(order, expected);
+        assert_eq!(
+            order.iter().map(|(created_at, _)| *created_at).collect::<Vec<_>>(),
+            [3_000, 3_000, 2_000, 2_000, 1_000]
+        );
+
+        let first = futures::executor::block_on(list_proofs_page(&storage, &wasm_hash, Some(3), None)).unwrap();
+        assert_eq!(first.proofs.len(), 3);
+        let rest = futures::executor::block_on(list_proofs_page(&storage, &wasm_hash, Some(3), first.cursor)).unwrap();
+        assert_eq!(rest.proofs.len(), 2);
+        assert_eq!(rest.cursor, None);
+    }
+
+    // Only a module hash lists proofs, other paths such as the owner index are a 400
+    #[test]
+    fn list_proofs_unit_test_1() {
+        let module = identity_module();
+        let wasm_hash = sha256_hex(&module);
+        let (wasm, proofs) = (MemoryStorage::default(), MemoryStorage::default());
+        wasm.insert(wasm_hash.clone(), module);
+        let owned = Proof {
+            owner: ProofOwner::GithubId(1234),
+            ..proof_for(&wasm_hash, 42, 42)
+        };
+        futures::executor::block_on(upload_proof(&wasm, &proofs, &request_id(), owned))
+            .ok()
+            .unwrap();
+
+        let Json(page) = futures::executor::block_on(list_proofs(&proofs, &wasm_hash, list_params("").unwrap()))
+            .ok()
+            .unwrap();
+        assert_eq!(page.proofs.len(), 1);
+        for path in ["by-owner", "by-owner/1234", &wasm_hash[..10]] {
+            assert_eq!(
+                error_status(futures::executor::block_on(list_proofs(
+                    &proofs,
+                    path,
+                    list_params("").unwrap()
+                ))),
+                StatusCode::BAD_REQUEST
+            );
+        }
+    }
+
+    // The configured binding wins unless it is missing or blank
+    #[test]
+    fn bucket_binding_unit_test_1() {
+        assert_eq!(bucket_binding(None, "wasm"), "wasm");
+        assert_eq!(bucket_binding(Some("   ".to_string()), "wasm"), "wasm");
+        assert_eq!(
+            bucket_binding(Some(" wasm-staging ".to_string()), "wasm"),
+            "wasm-staging"
+        );
+
+        let error = AppError::missing_bucket("wasm-staging");
+        assert_eq!(error.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(error.message, "R2 bucket binding `wasm-staging` is not configured");
+    }
 }
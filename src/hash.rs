@@ -0,0 +1,71 @@
+use sha2::{Digest, Sha256};
+
+/// Lowercase hex SHA-256 of `bytes`. Uploaded modules and proofs are stored under these hashes.
+pub fn sha256_hex(bytes: impl AsRef<[u8]>) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// [`sha256_hex`] of data that arrives in chunks, fed in as it comes.
+#[derive(Default)]
+pub struct IncrementalSha256(Sha256);
+
+impl IncrementalSha256 {
+    pub fn update(&mut self, chunk: impl AsRef<[u8]>) {
+        self.0.update(chunk);
+    }
+
+    pub fn finish(self) -> String {
+        format!("{:x}", self.0.finalize())
+    }
+}
+
+/// Whether `s` looks like a hash produced by [`sha256_hex`].
+pub fn is_sha256_hex(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Known SHA-256 test vectors
+    #[test]
+    fn sha256_hex_unit_test_1() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    // Only lowercase 64 digit hex strings are hashes
+    #[test]
+    fn is_sha256_hex_unit_test_1() {
+        assert!(is_sha256_hex(&sha256_hex(b"abc")));
+        assert!(!is_sha256_hex(&sha256_hex(b"abc").to_uppercase()));
+        assert!(!is_sha256_hex(&sha256_hex(b"abc")[1..]));
+        assert!(!is_sha256_hex("../../etc/passwd"));
+        assert!(!is_sha256_hex(""));
+    }
+
+    // Hashing in chunks gives the same hash as hashing everything at once, however the data is split
+    #[test]
+    fn incremental_sha256_unit_test_1() {
+        let data = (0..10_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        for chunk_size in [1, 7, 64, 4096, data.len()] {
+            let mut hasher = IncrementalSha256::default();
+            for chunk in data.chunks(chunk_size) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finish(), sha256_hex(&data));
+        }
+        assert_eq!(IncrementalSha256::default().finish(), sha256_hex(b""));
+    }
+}
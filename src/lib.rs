@@ -1,16 +1,37 @@
 #![allow(non_snake_case)]
+mod api;
 mod app;
+mod client;
 mod components;
-mod github;
+pub mod github;
 
 #[cfg(feature = "ssr")]
 mod handlers;
+mod hash;
+#[cfg(feature = "ssr")]
+mod metrics;
+mod proof;
+#[cfg(feature = "ssr")]
+mod storage;
 mod wasm;
 
+// Debug builds log everything and release builds from `Info` up, unless `POT_LOG_LEVEL` (e.g. `warn`) is set when
+// building.
+#[cfg(any(feature = "hydrate", feature = "ssr"))]
+fn log_level() -> log::Level {
+    option_env!("POT_LOG_LEVEL")
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(if cfg!(debug_assertions) {
+            log::Level::Debug
+        } else {
+            log::Level::Info
+        })
+}
+
 #[cfg(feature = "hydrate")]
 #[wasm_bindgen::prelude::wasm_bindgen]
 pub fn hydrate() {
-    _ = console_log::init_with_level(log::Level::Debug);
+    _ = console_log::init_with_level(log_level());
     console_error_panic_hook::set_once();
     leptos::mount::hydrate_body(app::App);
 }
@@ -18,50 +39,60 @@ pub fn hydrate() {
 #[cfg(feature = "ssr")]
 mod ssr_imports {
     use crate::app::{shell, App};
-    use crate::handlers::{upload_proof_handler, upload_wasm_handler, validate_handler};
-    use axum::{
-        routing::{post, put},
-        Extension, Router,
-    };
+    use crate::handlers::{security_headers, stateless_routes, storage_routes, MAX_BODY_SIZE};
+    use axum::{extract::DefaultBodyLimit, middleware, routing::post, Extension, Router};
     use leptos::prelude::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
     use std::sync::Arc;
     use worker::{event, Context, Env, HttpRequest, Result};
 
-    fn router(env: Env) -> Router {
+    // The pages and the routes that work without the worker's bindings.
+    fn base_router() -> Router {
         let leptos_options = LeptosOptions::builder()
             .output_name("client")
             .site_pkg_dir("pkg")
             .build();
         let routes = generate_route_list(App);
 
-        // build our application with a route
-        let app: axum::Router<()> = Router::new()
+        Router::new()
             .leptos_routes(&leptos_options, routes, {
                 let leptos_options = leptos_options.clone();
                 move || shell(leptos_options.clone())
             })
-            .route("/api/*fn_name", post(leptos_axum::handle_server_fns))
-            .route("/validate", post(validate_handler))
-            .route("/upload_wasm", post(upload_wasm_handler))
-            .route("/upload_proof", put(upload_proof_handler))
+            .merge(stateless_routes())
             .with_state(leptos_options)
+    }
+
+    fn router(env: Env) -> Router {
+        // Server functions extract the Env too, for secrets, vars and buckets.
+        let with_env = Router::new()
+            .route("/api/*fn_name", post(leptos_axum::handle_server_fns))
+            .merge(storage_routes())
             .layer(Extension(Arc::new(env)));
+
+        let app: axum::Router<()> = base_router()
+            .merge(with_env)
+            .layer(DefaultBodyLimit::max(MAX_BODY_SIZE))
+            .layer(middleware::from_fn(security_headers));
         app
     }
 
+    // Runs once per worker instance, before any request.
     #[event(start)]
     fn register() {
+        _ = console_log::init_with_level(crate::log_level());
+        console_error_panic_hook::set_once();
         server_fn::axum::register_explicit::<crate::app::ExchangeToken>();
+        server_fn::axum::register_explicit::<crate::components::GetProof>();
+        server_fn::axum::register_explicit::<crate::components::ListProofs>();
+        server_fn::axum::register_explicit::<crate::components::ListMyProofs>();
+        server_fn::axum::register_explicit::<crate::components::UploadWasmModule>();
     }
 
     #[event(fetch)]
     async fn fetch(req: HttpRequest, env: Env, _ctx: Context) -> Result<axum::http::Response<axum::body::Body>> {
-        _ = console_log::init_with_level(log::Level::Debug);
         use tower_service::Service;
 
-        console_error_panic_hook::set_once();
-
         Ok(router(env).call(req).await?)
     }
 }
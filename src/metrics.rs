@@ -0,0 +1,100 @@
+//! Counters served at `GET /metrics` in the Prometheus text format.
+//!
+//! They live in the memory of a single worker instance. Cloudflare runs many instances and recycles them at will, so
+//! each scrape only sees what the instance that answered has counted since it started. Good enough for spotting
+//! trends, not for totals.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicU64,
+}
+
+impl Counter {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+pub static UPLOADS_ACCEPTED: Counter = Counter::new("pot_uploads_accepted_total", "WASM modules accepted for storage.");
+pub static UPLOADS_REJECTED: Counter = Counter::new(
+    "pot_uploads_rejected_total",
+    "WASM uploads refused as not a module or not a valid test.",
+);
+pub static VALIDATIONS_PASSED: Counter = Counter::new(
+    "pot_validations_passed_total",
+    "Validations that returned a result, cached or not.",
+);
+pub static VALIDATIONS_FAILED: Counter = Counter::new(
+    "pot_validations_failed_total",
+    "Validations where the module failed to run.",
+);
+pub static BYTES_STORED: Counter = Counter::new("pot_bytes_stored_total", "Bytes of new WASM modules written to R2.");
+
+static COUNTERS: [&Counter; 5] = [
+    &UPLOADS_ACCEPTED,
+    &UPLOADS_REJECTED,
+    &VALIDATIONS_PASSED,
+    &VALIDATIONS_FAILED,
+    &BYTES_STORED,
+];
+
+/// Content type of [`render`]'s output.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+fn render_counters(counters: &[&Counter]) -> String {
+    counters.iter().fold(String::new(), |mut out, counter| {
+        let _ = write!(
+            out,
+            "# HELP {name} {}\n# TYPE {name} counter\n{name} {}\n",
+            counter.help,
+            counter.get(),
+            name = counter.name
+        );
+        out
+    })
+}
+
+/// All counters of this instance.
+pub fn render() -> String {
+    render_counters(&COUNTERS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Each counter gets its help and type lines followed by the current value
+    #[test]
+    fn render_unit_test_1() {
+        let uploads = Counter::new("uploads_total", "Uploads.");
+        let bytes = Counter::new("bytes_total", "Bytes.");
+        uploads.inc();
+        uploads.inc();
+        bytes.add(1024);
+        assert_eq!(
+            render_counters(&[&uploads, &bytes]),
+            "# HELP uploads_total Uploads.\n# TYPE uploads_total counter\nuploads_total 2\n\
+             # HELP bytes_total Bytes.\n# TYPE bytes_total counter\nbytes_total 1024\n"
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+use crate::api::ProofState;
+
+/// Who a proof is associated with.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum ProofOwner {
+    Anonymous,
+    GithubId(u64),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Proof {
+    pub wasm_hash: String,
+    pub owner: ProofOwner,
+    /// Upload time in milliseconds since the Unix epoch.
+    pub created_at: u64,
+    pub seed: u64,
+    /// What the module's `test` returns for `seed`, the only part of a proof the server checks by running it.
+    pub hash: u64,
+    pub weight: u32,
+    pub register: u32,
+    pub registers: u32,
+    /// How many runs the uploader says the proof stands for. One run with `seed` can't confirm that, so it is taken as
+    /// submitted, see [`Proof::content`].
+    pub count: u64,
+    /// Verdict of re-running the module with `seed`. Proofs stored before verdicts were recorded were only kept when
+    /// they checked out, so they read as verified.
+    #[serde(default = "verified")]
+    pub state: ProofState,
+}
+
+fn verified() -> ProofState {
+    ProofState::Verified
+}
+
+impl Proof {
+    /// The values that identify a proof, leaving out who uploaded it, when and the verdict. Uploading the same proof
+    /// twice yields the same content, so its hash can be used as the storage key.
+    pub fn content(&self) -> String {
+        format!(
+            "{}:{}:{}:{}:{}:{}:{}",
+            self.wasm_hash, self.seed, self.hash, self.weight, self.register, self.registers, self.count
+        )
+    }
+
+    /// SHA-256 of [`Proof::content`], what the proof is stored and linked under next to its module.
+    pub fn content_hash(&self) -> String {
+        crate::hash::sha256_hex(self.content())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_proof(owner: ProofOwner) -> Proof {
+        Proof {
+            wasm_hash: "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".into(),
+            owner,
+            created_at: 1_700_000_000_000,
+            seed: 42,
+            hash: 0x0000_1234_5678_9abc,
+            weight: 3,
+            register: 7,
+            registers: 64,
+            count: 1,
+            state: ProofState::Verified,
+        }
+    }
+
+    // Sanity check that `Proof` survives a JSON round trip
+    #[test]
+    fn proof_json_unit_test_1() {
+        let proof = sample_proof(ProofOwner::Anonymous);
+        let json = serde_json::to_string(&proof).unwrap();
+        let round_trip: Proof = serde_json::from_str(&json).unwrap();
+        assert_eq!(round_trip, proof);
+    }
+
+    // Verify that `Proof` can be deserialized from a hand-written JSON document, written before verdicts were stored
+    #[test]
+    fn proof_json_unit_test_2() {
+        let json = r#"{
+            "wasm_hash": "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            "owner": { "GithubId": 583231 },
+            "created_at": 1700000000000,
+            "seed": 42,
+            "hash": 20015998343868,
+            "weight": 3,
+            "register": 7,
+            "registers": 64,
+            "count": 1
+        }"#;
+        let proof: Proof = serde_json::from_str(json).unwrap();
+        assert_eq!(proof, sample_proof(ProofOwner::GithubId(583231)));
+    }
+
+    // Both owner variants round-trip
+    #[test]
+    fn proof_owner_json_unit_test_1() {
+        for owner in [ProofOwner::Anonymous, ProofOwner::GithubId(583231)] {
+            let json = serde_json::to_string(&owner).unwrap();
+            assert_eq!(serde_json::from_str::<ProofOwner>(&json).unwrap(), owner);
+        }
+        assert_eq!(serde_json::to_string(&ProofOwner::Anonymous).unwrap(), r#""Anonymous""#);
+        assert_eq!(
+            serde_json::to_string(&ProofOwner::GithubId(583231)).unwrap(),
+            r#"{"GithubId":583231}"#
+        );
+    }
+
+    // The content ignores who uploaded the proof and when
+    #[test]
+    fn proof_content_unit_test_1() {
+        let mut other = sample_proof(ProofOwner::GithubId(583231));
+        other.created_at += 1000;
+        assert_eq!(sample_proof(ProofOwner::Anonymous).content(), other.content());
+        other.seed += 1;
+        assert_ne!(sample_proof(ProofOwner::Anonymous).content(), other.content());
+    }
+
+    // The verdict is stored with the proof but doesn't change its content
+    #[test]
+    fn proof_state_unit_test_1() {
+        let mut rejected = sample_proof(ProofOwner::Anonymous);
+        rejected.state = ProofState::Rejected;
+        let json = serde_json::to_value(&rejected).unwrap();
+        assert_eq!(json["state"], "rejected");
+        assert_eq!(serde_json::from_value::<Proof>(json).unwrap(), rejected);
+        assert_eq!(rejected.content(), sample_proof(ProofOwner::Anonymous).content());
+    }
+}
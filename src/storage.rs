@@ -0,0 +1,101 @@
+use anyhow::Context as _;
+
+/// One page of keys from [`Storage::list`], in lexicographic order.
+pub struct KeyPage {
+    pub keys: Vec<String>,
+    /// Where the next page starts, `None` on the last page.
+    pub cursor: Option<String>,
+}
+
+/// Object storage keyed by string. The handlers go through this rather than R2 directly, so they can be tested against
+/// [`MemoryStorage`].
+pub trait Storage {
+    /// The object stored under `key`, if there is one.
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Stores `bytes` under `key`, replacing whatever was there.
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> anyhow::Result<()>;
+
+    /// Whether an object is stored under `key`, without fetching it.
+    async fn head(&self, key: &str) -> anyhow::Result<bool>;
+
+    /// Up to `limit` keys starting with `prefix`, continuing after a page when given its `cursor`.
+    async fn list(&self, prefix: &str, limit: u32, cursor: Option<String>) -> anyhow::Result<KeyPage>;
+}
+
+impl Storage for worker::Bucket {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(object) = worker::Bucket::get(self, key).execute().await? else {
+            return Ok(None);
+        };
+        Ok(Some(object.body().context("R2 object without body")?.bytes().await?))
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        worker::Bucket::put(self, key, bytes).execute().await?;
+        Ok(())
+    }
+
+    async fn head(&self, key: &str) -> anyhow::Result<bool> {
+        Ok(worker::Bucket::head(self, key).await?.is_some())
+    }
+
+    async fn list(&self, prefix: &str, limit: u32, cursor: Option<String>) -> anyhow::Result<KeyPage> {
+        let mut list = worker::Bucket::list(self).prefix(prefix.to_string()).limit(limit);
+        if let Some(cursor) = cursor {
+            list = list.cursor(cursor);
+        }
+        let objects = list.execute().await?;
+        Ok(KeyPage {
+            keys: objects.objects().into_iter().map(|object| object.key()).collect(),
+            cursor: if objects.truncated() { objects.cursor() } else { None },
+        })
+    }
+}
+
+/// In-memory stand-in for an R2 bucket.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryStorage {
+    objects: std::sync::Mutex<std::collections::HashMap<String, Vec<u8>>>,
+}
+
+#[cfg(test)]
+impl MemoryStorage {
+    pub fn insert(&self, key: impl Into<String>, bytes: impl Into<Vec<u8>>) {
+        self.objects.lock().unwrap().insert(key.into(), bytes.into());
+    }
+}
+
+#[cfg(test)]
+impl Storage for MemoryStorage {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.objects.lock().unwrap().get(key).cloned())
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        self.insert(key, bytes);
+        Ok(())
+    }
+
+    async fn head(&self, key: &str) -> anyhow::Result<bool> {
+        Ok(self.objects.lock().unwrap().contains_key(key))
+    }
+
+    // The cursor is the last key of the previous page.
+    async fn list(&self, prefix: &str, limit: u32, cursor: Option<String>) -> anyhow::Result<KeyPage> {
+        let mut keys = self
+            .objects
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|key| key.starts_with(prefix) && !cursor.as_ref().is_some_and(|cursor| *key <= cursor))
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.sort();
+        let truncated = keys.len() > limit as usize;
+        keys.truncate(limit as usize);
+        let cursor = if truncated { keys.last().cloned() } else { None };
+        Ok(KeyPage { keys, cursor })
+    }
+}
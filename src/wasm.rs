@@ -1,13 +1,294 @@
-use wasmi::{Engine, Linker, Module, Store};
+use anyhow::Context as _;
+#[cfg(any(feature = "ssr", test))]
+use std::borrow::Cow;
+use wasmi::core::TrapCode;
+use wasmi::{Config, Engine, ExternType, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
 
-pub fn run_test(wasm: &[u8], ident: &str, seed: u64) -> Result<u64, anyhow::Error> {
-    let engine = Engine::default();
-    let module = Module::new(&engine, &wasm)?;
-    let mut store = Store::new(&engine, ());
+/// Fuel available to a test, covering both its start function and the call itself. Roughly one unit is consumed per
+/// executed instruction.
+pub const TEST_FUEL: u64 = 50_000_000;
+
+/// Largest linear memory a test may have or grow to, 1024 pages of 64 KiB.
+pub const TEST_MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Largest table a test may have or grow to.
+pub const TEST_MAX_TABLE_ELEMENTS: u32 = 10_000;
+
+/// Returned when a test doesn't finish within [`TEST_FUEL`].
+#[derive(Debug)]
+pub struct OutOfFuel;
+
+impl std::fmt::Display for OutOfFuel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WASM test did not finish within {} units of fuel", TEST_FUEL)
+    }
+}
+
+impl std::error::Error for OutOfFuel {}
+
+fn check_fuel(error: wasmi::Error) -> anyhow::Error {
+    match error.as_trap_code() {
+        Some(TrapCode::OutOfFuel) => OutOfFuel.into(),
+        _ => error.into(),
+    }
+}
+
+/// The four bytes every binary WASM module starts with.
+pub const WASM_MAGIC: [u8; 4] = *b"\0asm";
+
+pub fn has_wasm_magic(bytes: &[u8]) -> bool {
+    bytes.starts_with(&WASM_MAGIC)
+}
+
+/// `bytes` as a binary module: unchanged if it already is one, otherwise parsed as the WAT text format. A parse error
+/// points at the line and column it stumbled on.
+#[cfg(any(feature = "ssr", test))]
+pub fn wat_to_wasm(bytes: &[u8]) -> Result<Cow<'_, [u8]>, anyhow::Error> {
+    if has_wasm_magic(bytes) {
+        return Ok(Cow::Borrowed(bytes));
+    }
+    wat::parse_bytes(bytes).map_err(|e| anyhow::anyhow!("Invalid WAT: {}", e))
+}
+
+/// What the validator reports about a module next to the test's result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ModuleInfo {
+    /// Length of the module in bytes.
+    pub size: usize,
+    /// Functions the module exports, whatever their signature.
+    pub exported_functions: usize,
+}
+
+// A test compiled and instantiated, ready to be called.
+struct Instance {
+    store: Store<StoreLimits>,
+    test: TypedFunc<u64, u64>,
+    info: ModuleInfo,
+}
+
+// Compiles and instantiates `wasm`, running its start function, and looks up `ident` as a `(u64) -> u64` export.
+fn instantiate(wasm: &[u8], ident: &str) -> Result<Instance, anyhow::Error> {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config);
+    let module = Module::new(&engine, wasm).context("Invalid WASM module")?;
+    let info = ModuleInfo {
+        size: wasm.len(),
+        exported_functions: module
+            .exports()
+            .filter(|export| matches!(export.ty(), ExternType::Func(_)))
+            .count(),
+    };
+    // Tests get no host functions, so an import could only fail later with a less helpful error.
+    let imports = module
+        .imports()
+        .map(|import| format!("{}::{}", import.module(), import.name()))
+        .collect::<Vec<_>>();
+    if !imports.is_empty() {
+        anyhow::bail!("module declares unsupported imports: {}", imports.join(", "));
+    }
+    // Growing past the limits traps rather than handing the module a -1 it might ignore.
+    let limits = StoreLimitsBuilder::new()
+        .memory_size(TEST_MAX_MEMORY_BYTES)
+        .table_elements(TEST_MAX_TABLE_ELEMENTS)
+        .trap_on_grow_failure(true)
+        .build();
+    let mut store = Store::new(&engine, limits);
+    store.limiter(|limits| limits);
+    store.set_fuel(TEST_FUEL).expect("fuel metering is enabled");
     let linker = Linker::new(&engine);
-    let instance = linker.instantiate(&mut store, &module)?.start(&mut store)?;
-    let test = instance.get_typed_func::<u64, u64>(&mut store, ident)?;
-    let result = test.call(&mut store, seed)?;
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .context("Failed to instantiate WASM module")?
+        .start(&mut store)
+        .map_err(check_fuel)
+        .context("WASM start function trapped")?;
+    let test = instance
+        .get_typed_func::<u64, u64>(&mut store, ident)
+        .with_context(|| format!("WASM module must export `{}` as a (u64) -> u64 function", ident))?;
+    Ok(Instance { store, test, info })
+}
+
+/// Checks that `wasm` instantiates and exports `ident` with the `(u64) -> u64` signature expected of a test.
+pub fn validate_test_module(wasm: &[u8], ident: &str) -> Result<(), anyhow::Error> {
+    instantiate(wasm, ident).map(|_| ())
+}
+
+pub fn run_test(wasm: &[u8], ident: &str, seed: u64) -> Result<u64, anyhow::Error> {
+    run_test_with_info(wasm, ident, seed).map(|(result, _)| result)
+}
+
+/// Like [`run_test`], also reporting the size and exports of the module.
+pub fn run_test_with_info(wasm: &[u8], ident: &str, seed: u64) -> Result<(u64, ModuleInfo), anyhow::Error> {
+    let Instance { mut store, test, info } = instantiate(wasm, ident)?;
+    let result = test.call(&mut store, seed).map_err(check_fuel)?;
     log::info!("Test result: {}", result);
-    Ok(result)
+    Ok((result, info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A module exporting `test: (u64) -> u64` is accepted and can be run
+    #[test]
+    fn validate_unit_test_1() {
+        let wasm = wat::parse_str(r#"(module (func (export "test") (param i64) (result i64) local.get 0))"#).unwrap();
+        assert!(validate_test_module(&wasm, "test").is_ok());
+        assert_eq!(run_test(&wasm, "test", 42).unwrap(), 42);
+    }
+
+    // Bytes that aren't a WASM module are rejected
+    #[test]
+    fn validate_unit_test_2() {
+        assert!(validate_test_module(b"not wasm", "test").is_err());
+    }
+
+    // A module without a `test` export, or with the wrong arity, is rejected
+    #[test]
+    fn validate_unit_test_3() {
+        let wasm = wat::parse_str("(module)").unwrap();
+        assert!(validate_test_module(&wasm, "test").is_err());
+
+        let wasm =
+            wat::parse_str(r#"(module (func (export "test") (param i64 i64) (result i64) local.get 0))"#).unwrap();
+        assert!(validate_test_module(&wasm, "test").is_err());
+    }
+
+    // A module whose start function traps is rejected
+    #[test]
+    fn validate_unit_test_4() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (func $boom unreachable)
+                (start $boom)
+                (func (export "test") (param i64) (result i64) local.get 0))"#,
+        )
+        .unwrap();
+        assert!(validate_test_module(&wasm, "test").is_err());
+    }
+
+    // A module importing anything from the host is rejected, naming the imports
+    #[test]
+    fn validate_unit_test_5() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "env" "foo" (func $foo (param i64) (result i64)))
+                (import "env" "memory" (memory 1))
+                (func (export "test") (param i64) (result i64) local.get 0 call $foo))"#,
+        )
+        .unwrap();
+        let error = validate_test_module(&wasm, "test").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "module declares unsupported imports: env::foo, env::memory"
+        );
+        assert!(run_test(&wasm, "test", 42).is_err());
+    }
+
+    // Memories and tables larger than the limits are rejected up front, and growing past them traps
+    #[test]
+    fn limits_unit_test_1() {
+        let wasm =
+            wat::parse_str(r#"(module (memory 1025) (func (export "test") (param i64) (result i64) local.get 0))"#)
+                .unwrap();
+        assert!(validate_test_module(&wasm, "test").is_err());
+
+        let wasm = wat::parse_str(
+            r#"(module (table 10001 funcref) (func (export "test") (param i64) (result i64) local.get 0))"#,
+        )
+        .unwrap();
+        assert!(validate_test_module(&wasm, "test").is_err());
+
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory 1)
+                (func (export "test") (param i64) (result i64)
+                    (drop (memory.grow (i32.wrap_i64 (local.get 0))))
+                    local.get 0))"#,
+        )
+        .unwrap();
+        assert!(validate_test_module(&wasm, "test").is_ok());
+        assert_eq!(run_test(&wasm, "test", 1023).unwrap(), 1023);
+        assert!(run_test(&wasm, "test", 1024).is_err());
+    }
+
+    // A test that never terminates runs out of fuel instead of hanging
+    #[test]
+    fn fuel_unit_test_1() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (func (export "test") (param i64) (result i64)
+                    (loop $spin (br $spin))
+                    unreachable))"#,
+        )
+        .unwrap();
+        let error = run_test(&wasm, "test", 42).unwrap_err();
+        assert!(error.is::<OutOfFuel>());
+    }
+
+    // An infinite loop in the start function is caught as well
+    #[test]
+    fn fuel_unit_test_2() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (func $spin (loop $spin (br $spin)))
+                (start $spin)
+                (func (export "test") (param i64) (result i64) local.get 0))"#,
+        )
+        .unwrap();
+        let error = validate_test_module(&wasm, "test").unwrap_err();
+        assert!(error.root_cause().is::<OutOfFuel>());
+    }
+
+    // Modules start with the WASM magic, other blobs don't
+    #[test]
+    fn magic_unit_test_1() {
+        let wasm = wat::parse_str("(module)").unwrap();
+        assert!(has_wasm_magic(&wasm));
+        assert!(has_wasm_magic(&[0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00]));
+        assert!(!has_wasm_magic(b"\x7fELF\x02\x01\x01"));
+        assert!(!has_wasm_magic(b"\0as"));
+        assert!(!has_wasm_magic(b""));
+    }
+
+    // Every exported function counts, the test included, while other exports don't
+    #[test]
+    fn module_info_unit_test_1() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "helper") (result i32) i32.const 0)
+                (func (export "test") (param i64) (result i64) local.get 0))"#,
+        )
+        .unwrap();
+        let (result, info) = run_test_with_info(&wasm, "test", 7).unwrap();
+        assert_eq!(result, 7);
+        assert_eq!(
+            info,
+            ModuleInfo {
+                size: wasm.len(),
+                exported_functions: 2,
+            }
+        );
+    }
+
+    // Binary modules pass through untouched, WAT is compiled to the same module
+    #[test]
+    fn wat_unit_test_1() {
+        let text = r#"(module (func (export "test") (param i64) (result i64) local.get 0))"#;
+        let wasm = wat::parse_str(text).unwrap();
+        assert!(matches!(wat_to_wasm(&wasm).unwrap(), Cow::Borrowed(bytes) if bytes == wasm.as_slice()));
+        assert_eq!(wat_to_wasm(text.as_bytes()).unwrap().as_ref(), wasm.as_slice());
+    }
+
+    // Broken WAT is refused with the line and column of the mistake
+    #[test]
+    fn wat_unit_test_2() {
+        let error =
+            wat_to_wasm(b"(module\n  (func (export \"test\") (param i64) (result i64) local.get 0)").unwrap_err();
+        let message = error.to_string();
+        assert!(message.starts_with("Invalid WAT: "), "{}", message);
+        assert!(message.contains(":2:"), "{}", message);
+    }
 }
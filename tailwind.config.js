@@ -1,6 +1,8 @@
 /** @type {import('tailwindcss').Config} */
 module.exports = {
   content: ["*.html", "./src/**/*.rs",],
+  // Dark variants follow the `dark` class on <body>, which the theme toggle sets.
+  darkMode: "class",
   theme: {
     extend: {},
   },